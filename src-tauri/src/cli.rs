@@ -0,0 +1,153 @@
+use desktop_postflop::config::*;
+use desktop_postflop::dump::*;
+use desktop_postflop::job::*;
+use postflop_solver::*;
+use rayon::ThreadPoolBuilder;
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::Mutex;
+
+#[derive(Deserialize)]
+struct SolveSpec {
+    oop_range: String,
+    ip_range: String,
+    board: String,
+    #[serde(flatten)]
+    tree: TreeConfigSpec,
+    #[serde(default = "default_target_exploitability")]
+    target_exploitability: f32,
+    #[serde(default = "default_max_iterations")]
+    max_iterations: u32,
+    #[serde(default)]
+    num_threads: usize,
+    #[serde(default)]
+    enable_compression: bool,
+    #[serde(default)]
+    compression_level: Option<i32>,
+    #[serde(default)]
+    output: Option<String>,
+    #[serde(default)]
+    dump: Option<String>,
+    #[serde(default)]
+    dump_chance_cards: Option<String>,
+}
+
+// same defaults as the GUI (percentage of the starting pot)
+fn default_target_exploitability() -> f32 {
+    0.3
+}
+
+fn default_max_iterations() -> u32 {
+    1000
+}
+
+fn parse_board(board: &str) -> Result<Vec<u8>, String> {
+    let chars = board
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<Vec<_>>();
+    chars
+        .chunks(2)
+        .map(|c| card_from_str(&c.iter().collect::<String>()))
+        .collect()
+}
+
+fn run(spec_path: &str, output: Option<String>) -> Result<(), String> {
+    let spec = std::fs::read_to_string(spec_path).map_err(|e| e.to_string())?;
+    let is_toml = Path::new(spec_path)
+        .extension()
+        .map_or(false, |ext| ext.eq_ignore_ascii_case("toml"));
+    let spec: SolveSpec = match is_toml {
+        true => toml::from_str(&spec).map_err(|e| e.to_string())?,
+        false => serde_json::from_str(&spec).map_err(|e| e.to_string())?,
+    };
+
+    let board = parse_board(&spec.board)?;
+    let state = board_state(board.len()).map_err(|e| e.to_string())?;
+    let ranges = [
+        Range::from_sanitized_str(&spec.oop_range)?,
+        Range::from_sanitized_str(&spec.ip_range)?,
+    ];
+
+    check_spec(spec.tree.validate()).map_err(|e| e.to_string())?;
+    let card_config = card_config(&ranges, &board).map_err(|e| e.to_string())?;
+    let action_tree = spec.tree.action_tree(state).map_err(|e| e.to_string())?;
+    let game = PostFlopGame::with_config(card_config, action_tree)?;
+
+    let (memory_usage, memory_usage_compressed) = game.memory_usage();
+    let memory_usage = match spec.enable_compression {
+        false => memory_usage,
+        true => memory_usage_compressed,
+    };
+    println!(
+        "Memory usage: {:.2}GB",
+        memory_usage as f64 / (1024.0 * 1024.0 * 1024.0)
+    );
+
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(spec.num_threads)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let starting_pot = spec.tree.starting_pot as f32;
+    let job = Mutex::new(SolveJob::default());
+    job.lock().unwrap().start(SolveOptions {
+        target_exploitability: starting_pot * spec.target_exploitability / 100.0,
+        max_iterations: spec.max_iterations,
+        ..Default::default()
+    });
+
+    let game = Mutex::new(game);
+    pool.install(|| {
+        game.lock().unwrap().allocate_memory(spec.enable_compression);
+        run_solve(&game, &job, |progress| {
+            let (iteration, exploitability) = (progress.iteration, progress.exploitability);
+            let percent = exploitability * 100.0 / starting_pot;
+            match progress.status {
+                SolveStatus::Running if iteration == 0 => {}
+                SolveStatus::Running => println!(
+                    "Iteration {iteration}: exploitability {exploitability:.2} ({percent:.2}%) [{:.2}s]",
+                    progress.elapsed
+                ),
+                _ => println!(
+                    "Finished {iteration} iterations: exploitability {exploitability:.2} ({percent:.2}%) [{:.2}s]",
+                    progress.elapsed
+                ),
+            }
+        })
+    })
+    .map_err(|e| e.to_string())?;
+    let mut game = game.into_inner().unwrap();
+
+    if let Some(path) = &spec.dump {
+        let chance_cards = spec.dump_chance_cards.as_deref().map(parse_board);
+        let options = DumpOptions {
+            chance_cards: chance_cards.transpose()?,
+        };
+        let num_nodes = dump_tree_to_file(&mut game, path, &options).map_err(|e| e.to_string())?;
+        println!("Dumped {num_nodes} nodes to {path}");
+    }
+
+    if let Some(path) = output.or(spec.output) {
+        save_data_to_file(&game, &spec.board, &path, spec.compression_level)?;
+        println!("Saved solution to {path}");
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let spec_path = match args.next() {
+        Some(path) => path,
+        None => {
+            eprintln!("Usage: postflop-cli <spec.json|spec.toml> [output]");
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(e) = run(&spec_path, args.next()) {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+}
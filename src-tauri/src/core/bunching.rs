@@ -0,0 +1,36 @@
+use postflop_solver::*;
+
+/// Advances the bunching effect computation by one step and returns the new `[phase, percent]`.
+///
+/// This function is computationally heavy; call it inside a thread pool.
+pub fn bunching_step(bunching_data: &mut BunchingData) -> [u8; 2] {
+    let phase = bunching_data.phase();
+    let percent = bunching_data.progress_percent();
+    if phase == 3 && percent == 100 {
+        return [3, 100];
+    }
+
+    if phase == 0 {
+        bunching_data.phase1_prepare();
+    } else if phase == 1 {
+        if percent < 100 {
+            bunching_data.phase1_proceed_by_percent();
+        } else {
+            bunching_data.phase2_prepare();
+        }
+    } else if phase == 2 {
+        if percent < 100 {
+            bunching_data.phase2_proceed_by_percent();
+        } else {
+            bunching_data.phase3_prepare();
+        }
+    } else if phase == 3 {
+        bunching_data.phase3_proceed_by_percent();
+    }
+
+    if phase == 0 || percent == 100 {
+        [phase + 1, 0]
+    } else {
+        [phase, percent + 1]
+    }
+}
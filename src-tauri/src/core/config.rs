@@ -0,0 +1,321 @@
+use crate::error::*;
+use crate::line::*;
+use crate::range::*;
+use postflop_solver::*;
+use serde::{Deserialize, Serialize};
+
+/// Upper limit of the starting pot and the effective stack, as in the tree configuration UI.
+pub const MAX_AMOUNT: i32 = 30000;
+
+/// Tree parameters shared by the tree editor, the game setup and the command-line solver.
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TreeConfigSpec {
+    pub starting_pot: i32,
+    pub effective_stack: i32,
+    pub rake_rate: f64,
+    pub rake_cap: f64,
+    pub donk_option: bool,
+    pub oop_flop_bet: String,
+    pub oop_flop_raise: String,
+    pub oop_turn_bet: String,
+    pub oop_turn_raise: String,
+    pub oop_turn_donk: String,
+    pub oop_river_bet: String,
+    pub oop_river_raise: String,
+    pub oop_river_donk: String,
+    pub ip_flop_bet: String,
+    pub ip_flop_raise: String,
+    pub ip_turn_bet: String,
+    pub ip_turn_raise: String,
+    pub ip_river_bet: String,
+    pub ip_river_raise: String,
+    /// Thresholds are ratios, e.g. 0.1 for 10%.
+    pub add_allin_threshold: f64,
+    pub force_allin_threshold: f64,
+    pub merging_threshold: f64,
+    pub added_lines: String,
+    pub removed_lines: String,
+}
+
+/// Finds the position of the first size in `sizes` that cannot be parsed by `parse`.
+fn invalid_bet_size<T>(
+    field: &str,
+    sizes: &str,
+    message: String,
+    parse: impl Fn(&str) -> Result<T, String>,
+) -> Error {
+    let position = sizes
+        .split(',')
+        .position(|size| parse(size).is_err())
+        .unwrap_or(0);
+    Error::InvalidBetSize {
+        field: field.to_string(),
+        position,
+        message,
+    }
+}
+
+fn bet_sizes(
+    (bet_field, bet): (&str, &str),
+    (raise_field, raise): (&str, &str),
+) -> Result<BetSizeOptions, Error> {
+    BetSizeOptions::try_from((bet, "")).map_err(|e| {
+        invalid_bet_size(bet_field, bet, e, |size| {
+            BetSizeOptions::try_from((size, ""))
+        })
+    })?;
+    BetSizeOptions::try_from(("", raise)).map_err(|e| {
+        invalid_bet_size(raise_field, raise, e, |size| {
+            BetSizeOptions::try_from(("", size))
+        })
+    })?;
+    BetSizeOptions::try_from((bet, raise)).map_err(Error::from)
+}
+
+fn donk_sizes(
+    donk_option: bool,
+    (donk_field, donk): (&str, &str),
+) -> Result<Option<DonkSizeOptions>, Error> {
+    match donk_option {
+        false => Ok(None),
+        true => DonkSizeOptions::try_from(donk).map(Some).map_err(|e| {
+            invalid_bet_size(donk_field, donk, e, |size| DonkSizeOptions::try_from(size))
+        }),
+    }
+}
+
+impl TreeConfigSpec {
+    pub fn tree_config(&self, initial_state: BoardState) -> Result<TreeConfig, Error> {
+        Ok(TreeConfig {
+            initial_state,
+            starting_pot: self.starting_pot,
+            effective_stack: self.effective_stack,
+            rake_rate: self.rake_rate,
+            rake_cap: self.rake_cap,
+            flop_bet_sizes: [
+                bet_sizes(
+                    ("oop_flop_bet", &self.oop_flop_bet),
+                    ("oop_flop_raise", &self.oop_flop_raise),
+                )?,
+                bet_sizes(
+                    ("ip_flop_bet", &self.ip_flop_bet),
+                    ("ip_flop_raise", &self.ip_flop_raise),
+                )?,
+            ],
+            turn_bet_sizes: [
+                bet_sizes(
+                    ("oop_turn_bet", &self.oop_turn_bet),
+                    ("oop_turn_raise", &self.oop_turn_raise),
+                )?,
+                bet_sizes(
+                    ("ip_turn_bet", &self.ip_turn_bet),
+                    ("ip_turn_raise", &self.ip_turn_raise),
+                )?,
+            ],
+            river_bet_sizes: [
+                bet_sizes(
+                    ("oop_river_bet", &self.oop_river_bet),
+                    ("oop_river_raise", &self.oop_river_raise),
+                )?,
+                bet_sizes(
+                    ("ip_river_bet", &self.ip_river_bet),
+                    ("ip_river_raise", &self.ip_river_raise),
+                )?,
+            ],
+            turn_donk_sizes: donk_sizes(self.donk_option, ("oop_turn_donk", &self.oop_turn_donk))?,
+            river_donk_sizes: donk_sizes(
+                self.donk_option,
+                ("oop_river_donk", &self.oop_river_donk),
+            )?,
+            add_allin_threshold: self.add_allin_threshold,
+            force_allin_threshold: self.force_allin_threshold,
+            merging_threshold: self.merging_threshold,
+        })
+    }
+
+    pub fn action_tree(&self, initial_state: BoardState) -> Result<ActionTree, Error> {
+        let mut tree = ActionTree::new(self.tree_config(initial_state)?)?;
+
+        if !self.added_lines.is_empty() {
+            for line in self.added_lines.split(',') {
+                if tree.add_line(&decode_line(line)?).is_err() {
+                    return Err(Error::invalid_line(
+                        line,
+                        "Failed to add line (loaded broken tree?)",
+                    ));
+                }
+            }
+        }
+
+        if !self.removed_lines.is_empty() {
+            for line in self.removed_lines.split(',') {
+                if tree.remove_line(&decode_line(line)?).is_err() {
+                    return Err(Error::invalid_line(
+                        line,
+                        "Failed to remove line (loaded broken tree?)",
+                    ));
+                }
+            }
+        }
+
+        Ok(tree)
+    }
+}
+
+/// Tree parameters and the board of a game.
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GameSpec {
+    pub board: Vec<u8>,
+    #[serde(flatten)]
+    pub tree: TreeConfigSpec,
+}
+
+impl TreeConfigSpec {
+    /// Bet size fields with whether they hold raise sizes.
+    fn bet_fields(&self) -> [(&'static str, &str, bool); 12] {
+        [
+            ("oop_flop_bet", &self.oop_flop_bet, false),
+            ("oop_flop_raise", &self.oop_flop_raise, true),
+            ("oop_turn_bet", &self.oop_turn_bet, false),
+            ("oop_turn_raise", &self.oop_turn_raise, true),
+            ("oop_river_bet", &self.oop_river_bet, false),
+            ("oop_river_raise", &self.oop_river_raise, true),
+            ("ip_flop_bet", &self.ip_flop_bet, false),
+            ("ip_flop_raise", &self.ip_flop_raise, true),
+            ("ip_turn_bet", &self.ip_turn_bet, false),
+            ("ip_turn_raise", &self.ip_turn_raise, true),
+            ("ip_river_bet", &self.ip_river_bet, false),
+            ("ip_river_raise", &self.ip_river_raise, true),
+        ]
+    }
+
+    /// Checks every field and returns all the problems found (empty if the spec is valid). Lines
+    /// are checked only for syntax, since whether they fit is known only after building the tree.
+    pub fn validate(&self) -> Vec<Error> {
+        let mut errors = Vec::new();
+
+        let amounts = [
+            ("starting_pot", "Starting pot", self.starting_pot),
+            ("effective_stack", "Effective stack", self.effective_stack),
+        ];
+        for (field, name, amount) in amounts {
+            if !(1..=MAX_AMOUNT).contains(&amount) {
+                let message = format!("{name} must be between 1 and {MAX_AMOUNT}");
+                errors.push(Error::invalid_field(field, message));
+            }
+        }
+
+        if !(0.0..=1.0).contains(&self.rake_rate) {
+            errors.push(Error::invalid_field(
+                "rake_rate",
+                "Rake rate must be between 0 and 1",
+            ));
+        }
+        if !(0.0..=3.0 * MAX_AMOUNT as f64).contains(&self.rake_cap) {
+            let message = format!("Rake cap must be between 0 and {}", 3 * MAX_AMOUNT);
+            errors.push(Error::invalid_field("rake_cap", message));
+        }
+
+        let thresholds = [
+            (
+                "add_allin_threshold",
+                "Add all-in threshold",
+                self.add_allin_threshold,
+            ),
+            (
+                "force_allin_threshold",
+                "Force all-in threshold",
+                self.force_allin_threshold,
+            ),
+            (
+                "merging_threshold",
+                "Merging threshold",
+                self.merging_threshold,
+            ),
+        ];
+        // thresholds are ratios; the tree editor accepts up to `MAX_AMOUNT * 100` percent
+        for (field, name, threshold) in thresholds {
+            if !(0.0..=MAX_AMOUNT as f64).contains(&threshold) {
+                let message = format!("{name} must be between 0% and {}%", MAX_AMOUNT * 100);
+                errors.push(Error::invalid_field(field, message));
+            }
+        }
+
+        for (field, sizes, is_raise) in self.bet_fields() {
+            let parse = |size: &str| match is_raise {
+                false => BetSizeOptions::try_from((size, "")),
+                true => BetSizeOptions::try_from(("", size)),
+            };
+            if let Err(e) = parse(sizes) {
+                errors.push(invalid_bet_size(field, sizes, e, parse));
+            }
+        }
+
+        let donks = [
+            ("oop_turn_donk", &self.oop_turn_donk),
+            ("oop_river_donk", &self.oop_river_donk),
+        ];
+        for (field, sizes) in donks {
+            if let Err(e) = donk_sizes(self.donk_option, (field, sizes)) {
+                errors.push(e);
+            }
+        }
+
+        for lines in [&self.added_lines, &self.removed_lines] {
+            if !lines.is_empty() {
+                errors.extend(lines.split(',').filter_map(|line| decode_line(line).err()));
+            }
+        }
+
+        errors
+    }
+}
+
+impl GameSpec {
+    /// Same as `TreeConfigSpec::validate`, but also checks the board.
+    pub fn validate(&self) -> Vec<Error> {
+        let mut errors = Vec::new();
+        if let Err(e) = board_state(self.board.len()) {
+            errors.push(e);
+        } else if let Err(e) = card_mask(&self.board) {
+            errors.push(e);
+        }
+        errors.extend(self.tree.validate());
+        errors
+    }
+}
+
+/// Returns the first problem reported by `validate`, if any.
+pub fn check_spec(errors: Vec<Error>) -> Result<(), Error> {
+    match errors.into_iter().next() {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+pub fn board_state(board_len: usize) -> Result<BoardState, Error> {
+    match board_len {
+        3 => Ok(BoardState::Flop),
+        4 => Ok(BoardState::Turn),
+        5 => Ok(BoardState::River),
+        _ => Err(Error::invalid_board("Invalid board length")),
+    }
+}
+
+pub fn card_config(ranges: &[Range], board: &[u8]) -> Result<CardConfig, Error> {
+    let (turn, river) = match board.len() {
+        3 => (NOT_DEALT, NOT_DEALT),
+        4 => (board[3], NOT_DEALT),
+        5 => (board[3], board[4]),
+        _ => return Err(Error::invalid_board("Invalid board length")),
+    };
+
+    Ok(CardConfig {
+        range: ranges[..2].try_into().unwrap(),
+        flop: board[..3].try_into().unwrap(),
+        turn,
+        river,
+    })
+}
@@ -0,0 +1,170 @@
+use crate::error::*;
+use crate::line::*;
+use crate::solver::*;
+use postflop_solver::*;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct DumpOptions {
+    /// Cards to deal at chance nodes (all possible cards if `None`). Cards that are not possible
+    /// at a chance node are skipped, so the walk covers only the runouts made of these cards.
+    #[serde(default)]
+    pub chance_cards: Option<Vec<u8>>,
+}
+
+/// One line of a strategy dump. See the README for the file format.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum DumpRecord<'a> {
+    Header {
+        board: Vec<String>,
+        hands: [Vec<String>; 2],
+    },
+    Node {
+        line: String,
+        board: Vec<String>,
+        player: &'static str,
+        actions: Vec<String>,
+        strategy: &'a [f32],
+    },
+}
+
+/// Writes a strategy dump as JSON Lines, one record at a time.
+pub struct DumpWriter<W: Write> {
+    writer: W,
+    num_nodes: usize,
+}
+
+impl<W: Write> DumpWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            num_nodes: 0,
+        }
+    }
+
+    fn write(&mut self, record: &DumpRecord) -> Result<(), Error> {
+        serde_json::to_writer(&mut self.writer, record).map_err(|e| e.to_string())?;
+        writeln!(self.writer).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Returns the number of decision nodes written and the underlying writer.
+    pub fn finish(mut self) -> Result<(usize, W), Error> {
+        self.writer.flush().map_err(|e| e.to_string())?;
+        Ok((self.num_nodes, self.writer))
+    }
+}
+
+fn board_strings(board: &[Card]) -> Result<Vec<String>, Error> {
+    board
+        .iter()
+        .map(|&card| Ok(card_to_string(card)?))
+        .collect()
+}
+
+fn walk<W: Write>(
+    game: &mut PostFlopGame,
+    line: &mut Vec<Action>,
+    options: &DumpOptions,
+    writer: &mut DumpWriter<W>,
+) -> Result<(), Error> {
+    if game.is_terminal_node() {
+        return Ok(());
+    }
+
+    let history = game.history().to_vec();
+    let board = game.current_board();
+
+    if game.is_chance_node() {
+        // strategies of the later streets are not stored
+        if board.len() >= storage_board_len(game) {
+            return Ok(());
+        }
+
+        let possible = game.possible_cards();
+        for card in 0..52 {
+            let allowed = options
+                .chance_cards
+                .as_ref()
+                .map_or(true, |cards| cards.contains(&card));
+            if possible & (1 << card) != 0 && allowed {
+                game.play(card as usize);
+                walk(game, line, options, writer)?;
+                game.apply_history(&history);
+            }
+        }
+
+        return Ok(());
+    }
+
+    let actions = game.available_actions();
+    let strategy = game.strategy();
+
+    writer.write(&DumpRecord::Node {
+        line: encode_line(line),
+        board: board_strings(&board)?,
+        player: if game.current_player() == 0 {
+            "oop"
+        } else {
+            "ip"
+        },
+        actions: actions.iter().map(|&a| encode_action(a)).collect(),
+        strategy: &strategy,
+    })?;
+    writer.num_nodes += 1;
+    drop(strategy);
+
+    for (index, &action) in actions.iter().enumerate() {
+        line.push(action);
+        game.play(index);
+        walk(game, line, options, writer)?;
+        game.apply_history(&history);
+        line.pop();
+    }
+
+    Ok(())
+}
+
+/// Walks every decision node of the solved `game` in depth-first order and writes its strategy
+/// to `writer`. The current node of `game` is restored afterwards.
+pub fn dump_tree<W: Write>(
+    game: &mut PostFlopGame,
+    writer: &mut DumpWriter<W>,
+    options: &DumpOptions,
+) -> Result<(), Error> {
+    if !game.is_solved() {
+        return Err(Error::not_initialized("Game is not solved"));
+    }
+
+    let history = game.history().to_vec();
+    game.back_to_root();
+
+    let hands = [
+        holes_to_strings(game.private_cards(0))?,
+        holes_to_strings(game.private_cards(1))?,
+    ];
+    writer.write(&DumpRecord::Header {
+        board: board_strings(&game.current_board())?,
+        hands,
+    })?;
+
+    let result = walk(game, &mut Vec::new(), options, writer);
+    game.apply_history(&history);
+    result
+}
+
+/// Writes a strategy dump of `game` to `path` and returns the number of decision nodes.
+pub fn dump_tree_to_file(
+    game: &mut PostFlopGame,
+    path: &str,
+    options: &DumpOptions,
+) -> Result<usize, Error> {
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let mut writer = DumpWriter::new(BufWriter::new(file));
+    dump_tree(game, &mut writer, options)?;
+    let (num_nodes, _) = writer.finish()?;
+    Ok(num_nodes)
+}
@@ -0,0 +1,412 @@
+use crate::error::*;
+use crate::hand::*;
+use crate::range::*;
+use postflop_solver::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Equities of a range-vs-range matchup. Entries of combos and hand classes that are not in the
+/// range (or are blocked by the board and the dead cards) are `NaN`.
+#[derive(Serialize)]
+pub struct EquityResult {
+    pub equity: [f64; 2],
+    /// Indexed by `card_pair_to_index` (1326 entries).
+    pub combo_equity: [Vec<f64>; 2],
+    /// 13x13 grid in row-major order, in the same layout as the range editor.
+    pub class_equity: [Vec<f64>; 2],
+}
+
+struct Hand {
+    cards: (Card, Card),
+    mask: u64,
+    weight: f64,
+}
+
+#[inline]
+fn class_index((card1, card2): (Card, Card)) -> usize {
+    let (rank1, rank2) = ((card1 >> 2) as usize, (card2 >> 2) as usize);
+    let (high, low) = (rank1.max(rank2), rank1.min(rank2));
+    let suited = card1 & 3 == card2 & 3;
+    match (high == low, suited) {
+        (true, _) => (12 - high) * 13 + (12 - high),
+        (false, true) => (12 - high) * 13 + (12 - low),
+        (false, false) => (12 - low) * 13 + (12 - high),
+    }
+}
+
+fn range_hands(range: &Range, dead_mask: u64) -> Vec<Hand> {
+    range
+        .raw_data()
+        .iter()
+        .enumerate()
+        .filter_map(|(index, &weight)| {
+            let (card1, card2) = index_to_card_pair(index);
+            let mask = 1u64 << card1 | 1u64 << card2;
+            (weight > 0.0 && mask & dead_mask == 0).then_some(Hand {
+                cards: (card1, card2),
+                mask,
+                weight: weight as f64,
+            })
+        })
+        .collect()
+}
+
+/// Weighted sums over the opponent hands of one strength group or of all hands, with the sums
+/// per card for removing the hands that conflict with a given hand.
+#[derive(Clone)]
+struct CardSums {
+    total: f64,
+    per_card: [f64; 52],
+}
+
+impl CardSums {
+    fn new() -> Self {
+        Self {
+            total: 0.0,
+            per_card: [0.0; 52],
+        }
+    }
+
+    fn add(&mut self, hand: &Hand) {
+        self.total += hand.weight;
+        self.per_card[hand.cards.0 as usize] += hand.weight;
+        self.per_card[hand.cards.1 as usize] += hand.weight;
+    }
+
+    /// Sum over the hands that share no card with `cards`. `same` is the weight of the hand
+    /// with exactly the same cards (if it belongs to the sums), which is subtracted twice.
+    fn compatible(&self, cards: (Card, Card), same: f64) -> f64 {
+        self.total - self.per_card[cards.0 as usize] - self.per_card[cards.1 as usize] + same
+    }
+}
+
+/// Adds the pot share (win = 1, tie = 1/2) and the opponent weight of each hand of `hero`
+/// against `villain` on `board` to `share` and `total`.
+fn accumulate(
+    hero: &[(u32, usize)],
+    villain: &[(u32, usize)],
+    hands: [&[Hand]; 2],
+    villain_weight: &[f64],
+    share: &mut [f64],
+    total: &mut [f64],
+) {
+    let (hero_hands, villain_hands) = (hands[0], hands[1]);
+
+    let mut all = CardSums::new();
+    for &(_, i) in villain {
+        all.add(&villain_hands[i]);
+    }
+
+    let mut less = CardSums::new();
+    let mut equal = CardSums::new();
+    let mut less_end = 0;
+    let mut equal_strength = None;
+
+    for &(strength, i) in hero {
+        if equal_strength != Some(strength) {
+            while less_end < villain.len() && villain[less_end].0 < strength {
+                less.add(&villain_hands[villain[less_end].1]);
+                less_end += 1;
+            }
+            equal = CardSums::new();
+            let mut equal_end = less_end;
+            while equal_end < villain.len() && villain[equal_end].0 == strength {
+                equal.add(&villain_hands[villain[equal_end].1]);
+                equal_end += 1;
+            }
+            equal_strength = Some(strength);
+        }
+
+        let cards = hero_hands[i].cards;
+        let same = villain_weight[card_pair_to_index(cards.0, cards.1)];
+        share[i] += less.compatible(cards, 0.0) + 0.5 * equal.compatible(cards, same);
+        total[i] += all.compatible(cards, same);
+    }
+}
+
+/// Computes the equities of `ranges[0]` against `ranges[1]` on `board` (3 to 5 cards) by
+/// enumerating every runout. This function is computationally heavy; call it inside a thread
+/// pool.
+pub fn compute_equity(
+    ranges: [&Range; 2],
+    board: &[Card],
+    dead_cards: &[Card],
+) -> Result<EquityResult, Error> {
+    if !(3..=5).contains(&board.len()) {
+        return Err(Error::invalid_board("Board must have 3 to 5 cards"));
+    }
+
+    let dead_mask = card_mask(&[board, dead_cards].concat())?;
+
+    let hands = [
+        range_hands(ranges[0], dead_mask),
+        range_hands(ranges[1], dead_mask),
+    ];
+    if hands.iter().any(|h| h.is_empty()) {
+        return Err(Error::InvalidRange {
+            message: "Range is empty".to_string(),
+        });
+    }
+
+    let deck = (0..52)
+        .filter(|&c| dead_mask & 1 << c == 0)
+        .collect::<Vec<Card>>();
+    let runouts = match board.len() {
+        5 => vec![vec![]],
+        4 => deck.iter().map(|&c| vec![c]).collect(),
+        _ => (0..deck.len())
+            .flat_map(|i| {
+                (i + 1..deck.len())
+                    .map(|j| vec![deck[i], deck[j]])
+                    .collect::<Vec<_>>()
+            })
+            .collect(),
+    };
+
+    let mut combo_weight = [vec![0.0; 1326], vec![0.0; 1326]];
+    for player in 0..2 {
+        for hand in &hands[player] {
+            combo_weight[player][card_pair_to_index(hand.cards.0, hand.cards.1)] = hand.weight;
+        }
+    }
+
+    let zeros = || {
+        [0, 1].map(|player| {
+            (
+                vec![0.0; hands[player].len()],
+                vec![0.0; hands[player].len()],
+            )
+        })
+    };
+
+    let sums = runouts
+        .par_iter()
+        .map(|runout| {
+            let mut cards = board.to_vec();
+            cards.extend(runout);
+            let runout_mask = runout.iter().fold(0u64, |m, &c| m | 1 << c);
+
+            let strengths = [0, 1].map(|player| {
+                let mut strengths = hands[player]
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, hand)| hand.mask & runout_mask == 0)
+                    .map(|(i, hand)| {
+                        cards.push(hand.cards.0);
+                        cards.push(hand.cards.1);
+                        let strength = evaluate(&cards);
+                        cards.truncate(cards.len() - 2);
+                        (strength, i)
+                    })
+                    .collect::<Vec<_>>();
+                strengths.sort_unstable();
+                strengths
+            });
+
+            let mut sums = zeros();
+            for player in 0..2 {
+                let (share, total) = &mut sums[player];
+                accumulate(
+                    &strengths[player],
+                    &strengths[player ^ 1],
+                    [&hands[player], &hands[player ^ 1]],
+                    &combo_weight[player ^ 1],
+                    share,
+                    total,
+                );
+            }
+            sums
+        })
+        .reduce(zeros, |mut acc, sums| {
+            for (acc, sums) in acc.iter_mut().zip(sums.iter()) {
+                acc.0.iter_mut().zip(&sums.0).for_each(|(a, s)| *a += s);
+                acc.1.iter_mut().zip(&sums.1).for_each(|(a, s)| *a += s);
+            }
+            acc
+        });
+
+    let mut result = EquityResult {
+        equity: [0.0; 2],
+        combo_equity: [vec![f64::NAN; 1326], vec![f64::NAN; 1326]],
+        class_equity: [vec![f64::NAN; 13 * 13], vec![f64::NAN; 13 * 13]],
+    };
+
+    for player in 0..2 {
+        let (share, total) = &sums[player];
+        let mut class_sums = vec![(0.0, 0.0); 13 * 13];
+        let mut overall = (0.0, 0.0);
+
+        for (i, hand) in hands[player].iter().enumerate() {
+            if total[i] == 0.0 {
+                continue;
+            }
+            let index = card_pair_to_index(hand.cards.0, hand.cards.1);
+            result.combo_equity[player][index] = share[i] / total[i];
+
+            let class = &mut class_sums[class_index(hand.cards)];
+            class.0 += hand.weight * share[i];
+            class.1 += hand.weight * total[i];
+            overall.0 += hand.weight * share[i];
+            overall.1 += hand.weight * total[i];
+        }
+
+        for (equity, &(share, total)) in result.class_equity[player].iter_mut().zip(&class_sums) {
+            if total > 0.0 {
+                *equity = share / total;
+            }
+        }
+
+        result.equity[player] = overall.0 / overall.1;
+    }
+
+    Ok(result)
+}
+
+/// How `top_percent_range` ranks the combos.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(tag = "by", rename_all = "snake_case")]
+pub enum RankBy {
+    /// Equity against the range in the slot `opponent`.
+    Equity { opponent: usize },
+    /// Strength of the made hand on the board.
+    Strength,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TopPercentOptions {
+    pub board: Vec<Card>,
+    pub by: RankBy,
+    /// Percentiles of the ranking (0 = strongest, 100 = weakest).
+    pub from: f64,
+    pub to: f64,
+}
+
+/// Returns the combos of `range` whose position in the ranking lies between the percentiles
+/// `options.from` and `options.to`, keeping their weights. The position of a combo is the
+/// midpoint of its share of the total weight. Combos blocked by the board are dropped. This
+/// function is computationally heavy when ranking by equity; call it inside a thread pool.
+pub fn top_percent_range(
+    range: &Range,
+    opponent: Option<&Range>,
+    options: &TopPercentOptions,
+) -> Result<Range, Error> {
+    let (board, from, to) = (&options.board[..], options.from, options.to);
+    if !(0.0..=100.0).contains(&from) || !(from..=100.0).contains(&to) {
+        return Err(Error::InvalidRange {
+            message: "Percentiles must satisfy 0 <= from <= to <= 100".to_string(),
+        });
+    }
+
+    let dead_mask = card_mask(board)?;
+    let hands = range_hands(range, dead_mask);
+    let scores = match opponent {
+        Some(opponent) => {
+            let result = compute_equity([range, opponent], board, &[])?;
+            hands
+                .iter()
+                .map(|hand| result.combo_equity[0][card_pair_to_index(hand.cards.0, hand.cards.1)])
+                .collect::<Vec<_>>()
+        }
+        None => {
+            if board.len() < 3 {
+                return Err(Error::invalid_board("Board must have 3 to 5 cards"));
+            }
+            hands
+                .iter()
+                .map(|hand| evaluate(&[board, &[hand.cards.0, hand.cards.1]].concat()) as f64)
+                .collect()
+        }
+    };
+
+    let mut order = (0..hands.len()).collect::<Vec<_>>();
+    order.sort_by(|&i, &j| scores[j].total_cmp(&scores[i]));
+
+    let total = hands.iter().map(|hand| hand.weight).sum::<f64>();
+    let mut cumulative = 0.0;
+    let mut result = Range::new();
+
+    for i in order {
+        let hand = &hands[i];
+        let position = 100.0 * (cumulative + 0.5 * hand.weight) / total;
+        cumulative += hand.weight;
+        if from <= position && position < to {
+            result.set_weight_by_cards(hand.cards.0, hand.cards.1, hand.weight as f32);
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(hands: &[&str]) -> Range {
+        let mut data = vec![0.0; 1326];
+        for hand in hands {
+            let hand = cards(hand);
+            data[card_pair_to_index(hand[0], hand[1])] = 1.0;
+        }
+        Range::from_raw_data(&data).unwrap()
+    }
+
+    fn combo_equity(result: &EquityResult, player: usize, hand: &str) -> f64 {
+        let hand = cards(hand);
+        result.combo_equity[player][card_pair_to_index(hand[0], hand[1])]
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn river_winner() {
+        let ranges = [range(&["AhAd"]), range(&["KcKs"])];
+        let result = compute_equity([&ranges[0], &ranges[1]], &cards("2c7d9hJs3s"), &[]).unwrap();
+        assert_close(result.equity[0], 1.0);
+        assert_close(result.equity[1], 0.0);
+        assert_close(result.class_equity[0][0], 1.0);
+        assert_close(result.class_equity[1][14], 0.0);
+    }
+
+    #[test]
+    fn river_tie() {
+        let ranges = [range(&["2c3d"]), range(&["4h5s"])];
+        let result = compute_equity([&ranges[0], &ranges[1]], &cards("AcKdQhJsTc"), &[]).unwrap();
+        assert_close(result.equity[0], 0.5);
+        assert_close(result.equity[1], 0.5);
+        assert_close(combo_equity(&result, 0, "2c3d"), 0.5);
+        assert!(combo_equity(&result, 0, "2c3h").is_nan());
+    }
+
+    #[test]
+    fn overpair_against_underpair_on_flop() {
+        // the kings win with one of the two kings left, unless the other card is an ace
+        let ranges = [range(&["AhAd"]), range(&["KcKs"])];
+        let result = compute_equity([&ranges[0], &ranges[1]], &cards("2c7d9h"), &[]).unwrap();
+        assert_close(result.equity[1], 83.0 / 990.0);
+        assert_close(result.equity[0], 907.0 / 990.0);
+    }
+
+    #[test]
+    fn dead_cards() {
+        let ranges = [range(&["AhAd"]), range(&["KcKs"])];
+        let board = cards("2c7d9h3s");
+
+        let result = compute_equity([&ranges[0], &ranges[1]], &board, &[]).unwrap();
+        assert_close(result.equity[1], 2.0 / 44.0);
+
+        let result = compute_equity([&ranges[0], &ranges[1]], &board, &cards("Kh")).unwrap();
+        assert_close(result.equity[1], 1.0 / 43.0);
+
+        // a dead card removes the combos holding it
+        let ranges = [range(&["AhAd", "AcAs"]), range(&["KcKs"])];
+        let result = compute_equity([&ranges[0], &ranges[1]], &board, &cards("Ac")).unwrap();
+        assert!(combo_equity(&result, 0, "AcAs").is_nan());
+        assert_close(combo_equity(&result, 0, "AhAd"), 41.0 / 43.0);
+
+        // dead cards must not be on the board
+        let result = compute_equity([&ranges[0], &ranges[1]], &board, &cards("2c"));
+        assert!(matches!(result, Err(Error::InvalidBoard { .. })));
+    }
+}
@@ -0,0 +1,119 @@
+use serde::Serialize;
+use std::fmt;
+
+/// Error returned by the commands. Serialized as an object tagged with `kind`, so that the
+/// frontend can show `message` and point at the offending input.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Error {
+    InvalidBoard {
+        message: String,
+    },
+    InvalidBetSize {
+        field: String,
+        position: usize,
+        message: String,
+    },
+    InvalidLine {
+        line: String,
+        message: String,
+    },
+    InvalidField {
+        field: String,
+        message: String,
+    },
+    InvalidRange {
+        message: String,
+    },
+    Engine {
+        message: String,
+    },
+    NotInitialized {
+        message: String,
+    },
+    SolverState {
+        message: String,
+    },
+    InvalidSession {
+        session_id: u32,
+        message: String,
+    },
+}
+
+impl Error {
+    pub fn message(&self) -> &str {
+        match self {
+            Error::InvalidBoard { message }
+            | Error::InvalidBetSize { message, .. }
+            | Error::InvalidLine { message, .. }
+            | Error::InvalidField { message, .. }
+            | Error::InvalidRange { message }
+            | Error::Engine { message }
+            | Error::NotInitialized { message }
+            | Error::SolverState { message }
+            | Error::InvalidSession { message, .. } => message,
+        }
+    }
+
+    pub fn invalid_board(message: impl Into<String>) -> Self {
+        Error::InvalidBoard {
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_line(line: impl Into<String>, message: impl Into<String>) -> Self {
+        Error::InvalidLine {
+            line: line.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_field(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Error::InvalidField {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn not_initialized(message: impl Into<String>) -> Self {
+        Error::NotInitialized {
+            message: message.into(),
+        }
+    }
+
+    pub fn solver_state(message: impl Into<String>) -> Self {
+        Error::SolverState {
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_session(session_id: u32) -> Self {
+        Error::InvalidSession {
+            session_id,
+            message: format!("Session {session_id} not found"),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidBetSize {
+                field,
+                position,
+                message,
+            } => write!(f, "{message} ({field}, position {position})"),
+            Error::InvalidLine { line, message } => write!(f, "{message} ({line})"),
+            Error::InvalidField { field, message } => write!(f, "{message} ({field})"),
+            _ => f.write_str(self.message()),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::Engine { message }
+    }
+}
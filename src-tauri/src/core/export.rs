@@ -0,0 +1,195 @@
+use crate::error::*;
+use crate::line::*;
+use crate::solver::*;
+use postflop_solver::*;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+/// Results of one private hand at the current node. Equity, EV and EQR are `None` when either
+/// range is empty at the node; `strategy` and `action_ev` are empty unless the hand belongs to
+/// the acting player.
+#[derive(Serialize)]
+pub struct HandRow {
+    pub player: &'static str,
+    pub hand: String,
+    pub weight: f32,
+    pub normalized_weight: f32,
+    pub equity: Option<f32>,
+    pub ev: Option<f32>,
+    pub eqr: Option<f32>,
+    pub strategy: Vec<f32>,
+    pub action_ev: Vec<f32>,
+}
+
+#[derive(Serialize)]
+pub struct NodeExport {
+    pub board: Vec<String>,
+    pub history: Vec<usize>,
+    pub current_player: String,
+    /// Actions of the acting player in `encode_action` notation (empty at terminal and chance
+    /// nodes).
+    pub actions: Vec<String>,
+    pub hands: Vec<HandRow>,
+}
+
+/// Collects the unrounded results of the current node of `game`.
+pub fn node_export(game: &mut PostFlopGame) -> Result<NodeExport, Error> {
+    ensure_solved(game)?;
+
+    let total_bet_amount = game.total_bet_amount();
+    let pot_base = game.tree_config().starting_pot + total_bet_amount.iter().min().unwrap();
+
+    let is_empty = |player: usize| game.weights(player).iter().all(|&w| w == 0.0);
+    let has_results = !is_empty(0) && !is_empty(1);
+    if has_results {
+        game.cache_normalized_weights();
+    }
+
+    let is_decision = !game.is_terminal_node() && !game.is_chance_node();
+    let acting_player = game.current_player();
+    let actions = match is_decision {
+        true => game.available_actions(),
+        false => Vec::new(),
+    };
+    let num_actions = actions.len();
+
+    let (strategy, action_ev) = match is_decision {
+        true => (
+            game.strategy(),
+            match has_results {
+                true => game.expected_values_detail(acting_player),
+                false => Vec::new(),
+            },
+        ),
+        false => (Vec::new(), Vec::new()),
+    };
+
+    let mut hands = Vec::new();
+
+    for (player, &bet_amount) in total_bet_amount.iter().enumerate() {
+        let names = holes_to_strings(game.private_cards(player))?;
+        let weights = game.weights(player);
+        let num_hands = names.len();
+        let pot = (pot_base + bet_amount) as f32;
+
+        let (normalized, equity, ev) = match has_results {
+            true => (
+                game.normalized_weights(player).to_vec(),
+                game.equity(player),
+                game.expected_values(player),
+            ),
+            false => (weights.to_vec(), Vec::new(), Vec::new()),
+        };
+
+        let per_action = |values: &[f32], hand: usize| match values.is_empty() {
+            true => Vec::new(),
+            false => (0..num_actions)
+                .map(|action| values[action * num_hands + hand])
+                .collect(),
+        };
+
+        for (hand, name) in names.into_iter().enumerate() {
+            let (equity, ev) = (equity.get(hand).copied(), ev.get(hand).copied());
+            let eqr = match (equity, ev) {
+                (Some(eq), Some(ev)) if eq >= 5e-7 => Some(ev / (pot * eq)),
+                _ => None,
+            };
+
+            let is_acting = is_decision && player == acting_player;
+            hands.push(HandRow {
+                player: if player == 0 { "oop" } else { "ip" },
+                hand: name,
+                weight: weights[hand],
+                normalized_weight: normalized[hand],
+                equity,
+                ev,
+                eqr,
+                strategy: match is_acting {
+                    true => per_action(&strategy, hand),
+                    false => Vec::new(),
+                },
+                action_ev: match is_acting {
+                    true => per_action(&action_ev, hand),
+                    false => Vec::new(),
+                },
+            });
+        }
+    }
+
+    let board = game
+        .current_board()
+        .into_iter()
+        .map(card_to_string)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(NodeExport {
+        board,
+        history: game.history().to_vec(),
+        current_player: current_player(game),
+        actions: actions.into_iter().map(encode_action).collect(),
+        hands,
+    })
+}
+
+/// Writes `export` as CSV: one row per hand, followed by a `freq_<action>` and an `ev_<action>`
+/// column for each action of the acting player. Missing values are left empty.
+pub fn write_csv(export: &NodeExport, writer: &mut impl Write) -> std::io::Result<()> {
+    write!(writer, "player,hand,weight,normalized_weight,equity,ev,eqr")?;
+    for action in &export.actions {
+        write!(writer, ",freq_{action}")?;
+    }
+    for action in &export.actions {
+        write!(writer, ",ev_{action}")?;
+    }
+    writeln!(writer)?;
+
+    let optional = |value: Option<f32>| value.map_or(String::new(), |v| v.to_string());
+    let num_actions = export.actions.len();
+
+    for row in &export.hands {
+        write!(
+            writer,
+            "{},{},{},{},{},{},{}",
+            row.player,
+            row.hand,
+            row.weight,
+            row.normalized_weight,
+            optional(row.equity),
+            optional(row.ev),
+            optional(row.eqr)
+        )?;
+        for values in [&row.strategy, &row.action_ev] {
+            for action in 0..num_actions {
+                write!(writer, ",{}", optional(values.get(action).copied()))?;
+            }
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the results of the current node of `game` to `path`.
+pub fn export_node(game: &mut PostFlopGame, path: &str, format: ExportFormat) -> Result<(), Error> {
+    let export = node_export(game)?;
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let mut writer = BufWriter::new(file);
+
+    match format {
+        ExportFormat::Csv => write_csv(&export, &mut writer).map_err(|e| e.to_string())?,
+        ExportFormat::Json => {
+            serde_json::to_writer(&mut writer, &export).map_err(|e| e.to_string())?
+        }
+    }
+
+    writer.flush().map_err(|e| e.to_string())?;
+    Ok(())
+}
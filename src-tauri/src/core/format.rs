@@ -0,0 +1,372 @@
+use crate::error::*;
+use crate::range::*;
+use postflop_solver::*;
+use serde::{Deserialize, Serialize};
+
+/// Text formats of ranges.
+///
+/// - `sanitized`: the postflop-solver syntax, e.g. `"AA,KQs+,AhKh:0.5"`
+/// - `pio`: PioSOLVER weighted lists, e.g. `"AA,KK:0.5,AKs:0.25"`
+/// - `gtoplus`: GTO+ weight groups in percent, e.g. `"AA,[50.0]KK,AKs[/50.0]"`
+/// - `combos`: one entry per combo, e.g. `"AhKh:0.5,AsKs"`
+/// - `csv`: 13 lines of 13 comma-separated class weights, in the layout of the range editor;
+///   a cell is a fraction (`0.5`) or a percentage with an explicit sign (`50%`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RangeFormat {
+    Auto,
+    Sanitized,
+    Pio,
+    Gtoplus,
+    Combos,
+    Csv,
+}
+
+#[derive(Serialize)]
+pub struct RangeImportResult {
+    /// Detected format when importing with `auto`.
+    pub format: RangeFormat,
+    /// Tokens (or CSV cells) that could not be parsed and were ignored.
+    pub unparsed: Vec<String>,
+}
+
+fn is_combo(token: &str) -> bool {
+    let hand = token.split(':').next().unwrap();
+    let chars = hand.chars().collect::<Vec<_>>();
+    chars.len() == 4
+        && card_from_str(&chars[..2].iter().collect::<String>()).is_ok()
+        && card_from_str(&chars[2..].iter().collect::<String>()).is_ok()
+}
+
+fn csv_cells(text: &str) -> Option<Vec<Vec<&str>>> {
+    let rows = text
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            line.split([',', ';', '\t'])
+                .map(|cell| cell.trim())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let is_grid = rows.len() == 13 && rows.iter().all(|row| row.len() == 13);
+    is_grid.then_some(rows)
+}
+
+fn split_tokens(text: &str) -> Vec<String> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_string())
+        .collect()
+}
+
+pub fn detect_format(text: &str) -> RangeFormat {
+    if text.contains('[') {
+        RangeFormat::Gtoplus
+    } else if csv_cells(text).is_some() {
+        RangeFormat::Csv
+    } else {
+        let tokens = split_tokens(text);
+        if !tokens.is_empty() && tokens.iter().all(|token| is_combo(token)) {
+            RangeFormat::Combos
+        } else if text.contains(':') {
+            RangeFormat::Pio
+        } else {
+            RangeFormat::Sanitized
+        }
+    }
+}
+
+/// Expands GTO+ weight groups into `hand:weight` tokens.
+fn gtoplus_tokens(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut weight = None;
+
+    let mut flush = |token: &mut String, weight: Option<f32>| {
+        let trimmed = token.trim();
+        if !trimmed.is_empty() {
+            tokens.push(match weight {
+                Some(w) => format!("{trimmed}:{w}"),
+                None => trimmed.to_string(),
+            });
+        }
+        token.clear();
+    };
+
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ',' => flush(&mut token, weight),
+            '[' => {
+                flush(&mut token, weight);
+                let tag = chars.by_ref().take_while(|&c| c != ']').collect::<String>();
+                weight = match tag.starts_with('/') {
+                    true => None,
+                    // an unparsable tag is reported as a token
+                    false => match tag.trim().parse::<f32>() {
+                        Ok(w) => Some(w / 100.0),
+                        Err(_) => {
+                            token = format!("[{tag}]");
+                            flush(&mut token, None);
+                            weight
+                        }
+                    },
+                };
+            }
+            c => token.push(c),
+        }
+    }
+    flush(&mut token, weight);
+
+    tokens
+}
+
+/// Applies `hand[:weight]` tokens in order; later tokens override earlier ones, also with a
+/// weight of 0 (e.g. `AA,AhAd:0`).
+fn import_tokens(tokens: &[String], unparsed: &mut Vec<String>) -> Range {
+    let mut data = vec![0.0; 1326];
+    for token in tokens {
+        let (hand, weight) = match token.split_once(':') {
+            Some((hand, weight)) => (hand, weight.parse::<f32>().ok()),
+            None => (token.as_str(), Some(1.0)),
+        };
+        match (Range::from_sanitized_str(hand), weight) {
+            (Ok(hands), Some(weight)) if (0.0..=1.0).contains(&weight) => {
+                for (dst, &covered) in data.iter_mut().zip(hands.raw_data()) {
+                    if covered > 0.0 {
+                        *dst = weight;
+                    }
+                }
+            }
+            _ => unparsed.push(token.clone()),
+        }
+    }
+    Range::from_raw_data(&data).unwrap()
+}
+
+/// Parses a CSV cell: a fraction between 0 and 1, or a percentage between 0% and 100%.
+fn csv_weight(cell: &str) -> Option<f32> {
+    let (number, scale) = match cell.strip_suffix('%') {
+        Some(percent) => (percent.trim_end(), 100.0),
+        None => (cell, 1.0),
+    };
+    let weight = number.parse::<f32>().ok()? / scale;
+    (0.0..=1.0).contains(&weight).then_some(weight)
+}
+
+fn import_csv(text: &str, unparsed: &mut Vec<String>) -> Result<Range, Error> {
+    let rows = csv_cells(text).ok_or_else(|| Error::InvalidRange {
+        message: "CSV must have 13 rows of 13 cells".to_string(),
+    })?;
+
+    let mut weights = vec![0.0; 13 * 13];
+    for (index, cell) in rows.iter().flatten().enumerate() {
+        match csv_weight(cell) {
+            Some(weight) => weights[index] = weight,
+            None => unparsed.push(cell.to_string()),
+        }
+    }
+
+    let mut range = Range::new();
+    for (index, &weight) in weights.iter().enumerate() {
+        for (card1, card2) in class_combos(index / 13, index % 13) {
+            range.set_weight_by_cards(card1, card2, weight);
+        }
+    }
+    Ok(range)
+}
+
+/// Parses `text` in `format` (detected if `auto`). Unparsable tokens are reported and ignored;
+/// an error is returned only if nothing could be parsed.
+pub fn import_range(text: &str, format: RangeFormat) -> Result<(Range, RangeImportResult), Error> {
+    let format = match format {
+        RangeFormat::Auto => detect_format(text),
+        format => format,
+    };
+
+    let mut unparsed = Vec::new();
+    let range = match format {
+        RangeFormat::Csv => import_csv(text, &mut unparsed)?,
+        RangeFormat::Gtoplus => import_tokens(&gtoplus_tokens(text), &mut unparsed),
+        RangeFormat::Sanitized => match Range::from_sanitized_str(text) {
+            Ok(range) => range,
+            Err(_) => import_tokens(&split_tokens(text), &mut unparsed),
+        },
+        _ => import_tokens(&split_tokens(text), &mut unparsed),
+    };
+
+    if range.is_empty() && !unparsed.is_empty() {
+        return Err(Error::InvalidRange {
+            message: format!("Failed to parse: {}", unparsed.join(", ")),
+        });
+    }
+
+    Ok((range, RangeImportResult { format, unparsed }))
+}
+
+fn format_weight(weight: f32) -> String {
+    let s = format!("{:.3}", weight);
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn combo_name((card1, card2): (Card, Card)) -> String {
+    let (high, low) = (card1.max(card2), card1.min(card2));
+    card_to_string(high).unwrap() + &card_to_string(low).unwrap()
+}
+
+/// Hands of `range` with their weights: whole classes when suit-uniform, combos otherwise.
+fn weighted_hands(range: &Range, combos_only: bool) -> Vec<(String, f32)> {
+    let uniform = suit_uniform_flags(range);
+    let mut hands = Vec::new();
+
+    for (index, &uniform) in uniform.iter().enumerate() {
+        let combos = class_combos(index / 13, index % 13);
+        if uniform && !combos_only {
+            let weight = range.get_weight_by_cards(combos[0].0, combos[0].1);
+            if weight > 0.0 {
+                hands.push((class_name(index), weight));
+            }
+        } else {
+            for combo in combos {
+                let weight = range.get_weight_by_cards(combo.0, combo.1);
+                if weight > 0.0 {
+                    hands.push((combo_name(combo), weight));
+                }
+            }
+        }
+    }
+
+    hands
+}
+
+fn weighted_list(hands: &[(String, f32)]) -> String {
+    hands
+        .iter()
+        .map(|(hand, weight)| match *weight >= 1.0 {
+            true => hand.clone(),
+            false => format!("{hand}:{}", format_weight(*weight)),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn gtoplus_string(hands: &[(String, f32)]) -> String {
+    let mut groups: Vec<(String, Vec<&str>)> = Vec::new();
+    for (hand, weight) in hands {
+        let percent = format_weight(weight * 100.0);
+        match groups.iter_mut().find(|(p, _)| *p == percent) {
+            Some((_, group)) => group.push(hand),
+            None => groups.push((percent, vec![hand])),
+        }
+    }
+
+    groups
+        .iter()
+        .map(|(percent, group)| match percent.as_str() {
+            "100" => group.join(","),
+            _ => format!("[{percent}]{}[/{percent}]", group.join(",")),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+pub fn export_range(range: &Range, format: RangeFormat) -> String {
+    match format {
+        RangeFormat::Auto | RangeFormat::Sanitized => range.to_string(),
+        RangeFormat::Pio => weighted_list(&weighted_hands(range, false)),
+        RangeFormat::Combos => weighted_list(&weighted_hands(range, true)),
+        RangeFormat::Gtoplus => gtoplus_string(&weighted_hands(range, false)),
+        RangeFormat::Csv => class_weights(range)
+            .chunks(13)
+            .map(|row| {
+                row.iter()
+                    .map(|&w| format_weight(w))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(cell: impl Fn(usize, usize) -> String) -> String {
+        (0..13)
+            .map(|row| {
+                (0..13)
+                    .map(|col| cell(row, col))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn detect() {
+        assert_eq!(detect_format("AA,[50.0]KK[/50.0]"), RangeFormat::Gtoplus);
+        assert_eq!(
+            detect_format(&grid(|_, _| "0".to_string())),
+            RangeFormat::Csv
+        );
+        assert_eq!(detect_format("AhKh:0.5, AsKs"), RangeFormat::Combos);
+        assert_eq!(detect_format("AA,KK:0.5,AhKh"), RangeFormat::Pio);
+        assert_eq!(detect_format("AA,KQs+"), RangeFormat::Sanitized);
+        assert_eq!(detect_format("AA\nKK"), RangeFormat::Sanitized);
+    }
+
+    #[test]
+    fn gtoplus() {
+        assert_eq!(
+            gtoplus_tokens("AA,[50.0]KK,AKs[/50.0],QQ"),
+            ["AA", "KK:0.5", "AKs:0.5", "QQ"]
+        );
+        assert_eq!(gtoplus_tokens("[25]JJ[/25] , TT"), ["JJ:0.25", "TT"]);
+        assert_eq!(gtoplus_tokens("[abc]AA[/abc]"), ["[abc]", "AA"]);
+    }
+
+    #[test]
+    fn later_tokens_override() {
+        let (range, result) = import_range("AA,AhAd:0,KK:0.5,KK", RangeFormat::Pio).unwrap();
+        let card = |s| card_from_str(s).unwrap();
+        assert_eq!(range.get_weight_by_cards(card("Ah"), card("Ad")), 0.0);
+        assert_eq!(range.get_weight_by_cards(card("Ah"), card("As")), 1.0);
+        assert_eq!(range.get_weight_by_cards(card("Kh"), card("Kd")), 1.0);
+        assert!(result.unparsed.is_empty());
+
+        let (_, result) = import_range("AA,KK:1.5,QQ:x", RangeFormat::Pio).unwrap();
+        assert_eq!(result.unparsed, ["KK:1.5", "QQ:x"]);
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let text = grid(|row, col| match (row + col) % 3 {
+            0 => "0".to_string(),
+            1 => "0.5".to_string(),
+            _ => "1".to_string(),
+        });
+        let (range, result) = import_range(&text, RangeFormat::Auto).unwrap();
+        assert_eq!(result.format, RangeFormat::Csv);
+        assert!(result.unparsed.is_empty());
+        assert_eq!(export_range(&range, RangeFormat::Csv), text);
+    }
+
+    #[test]
+    fn csv_percentages() {
+        let percent = grid(|row, _| if row == 0 { "50%" } else { "100 %" }.to_string());
+        let fraction = grid(|row, _| if row == 0 { "0.5" } else { "1" }.to_string());
+        let (range, _) = import_range(&percent, RangeFormat::Csv).unwrap();
+        assert_eq!(export_range(&range, RangeFormat::Csv), fraction);
+
+        // without a sign, cells are fractions
+        let text = grid(|row, col| if (row, col) == (0, 0) { "50" } else { "1" }.to_string());
+        let (range, result) = import_range(&text, RangeFormat::Csv).unwrap();
+        assert_eq!(result.unparsed, ["50"]);
+        assert_eq!(class_weights(&range)[0], 0.0);
+        assert_eq!(class_weights(&range)[1], 1.0);
+    }
+}
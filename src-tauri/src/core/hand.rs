@@ -0,0 +1,533 @@
+use crate::error::*;
+use crate::line::*;
+use crate::solver::*;
+use postflop_solver::*;
+use serde::Serialize;
+
+/// Made-hand class from the point of view of the private cards. Pairs are relative to the
+/// distinct board ranks (top, second, lower), and only top pair has kicker detail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MadeHand {
+    StraightFlush,
+    Quads,
+    FullHouse,
+    Flush,
+    Straight,
+    Set,
+    Trips,
+    TwoPair,
+    Overpair,
+    TopPairTopKicker,
+    TopPairGoodKicker,
+    TopPairWeakKicker,
+    MiddlePair,
+    BottomPair,
+    Underpair,
+    HighCard,
+}
+
+/// Strongest draw of a hand. Draws are not reported on the river or for hands that already make
+/// a flush or better.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Draw {
+    /// Flush draw plus an open-ended straight draw or a gutshot.
+    ComboDraw,
+    FlushDraw,
+    /// Open-ended straight draw, including double gutshots.
+    Oesd,
+    Gutshot,
+    BackdoorFlushDraw,
+    BackdoorStraightDraw,
+    NoDraw,
+}
+
+#[inline]
+fn rank(card: Card) -> usize {
+    (card >> 2) as usize
+}
+
+#[inline]
+fn suit(card: Card) -> usize {
+    (card & 3) as usize
+}
+
+fn rank_mask(cards: impl Iterator<Item = Card>) -> u16 {
+    cards.fold(0, |mask, card| mask | 1 << rank(card))
+}
+
+/// Returns the highest rank of the best straight in `mask` (bit `r` set for rank `r`, 0 = deuce).
+/// A wheel returns 3 (five-high).
+fn straight_high(mask: u16) -> Option<usize> {
+    // the ace also plays as the lowest card
+    let extended = (mask << 1) | (mask >> 12 & 1);
+    (0..=9)
+        .rev()
+        .find(|&low| extended >> low & 0b11111 == 0b11111)
+        .map(|low| low + 3)
+}
+
+#[inline]
+fn has_straight(mask: u16) -> bool {
+    straight_high(mask).is_some()
+}
+
+/// Returns the strength of the best five-card hand made of `cards` (5 to 7 cards). A higher
+/// value is a stronger hand, and equal values split the pot.
+pub fn evaluate(cards: &[Card]) -> u32 {
+    let mut rank_count = [0u8; 13];
+    let mut suit_masks = [0u16; 4];
+    for &card in cards {
+        rank_count[rank(card)] += 1;
+        suit_masks[suit(card)] |= 1 << rank(card);
+    }
+
+    let mask = rank_mask(cards.iter().copied());
+    let value = |category: u32, ranks: &[usize]| {
+        let kickers = ranks.iter().take(5).fold(0, |acc, &r| acc << 4 | r as u32);
+        category << 20 | kickers << (4 * (5 - ranks.len().min(5)))
+    };
+
+    // ranks in descending order, grouped by count
+    let ranks_with = |count: u8| {
+        (0..13)
+            .rev()
+            .filter(|&r| rank_count[r] == count)
+            .collect::<Vec<_>>()
+    };
+    let kickers = |exclude: &[usize]| {
+        (0..13)
+            .rev()
+            .filter(|r| rank_count[*r] > 0 && !exclude.contains(r))
+            .collect::<Vec<_>>()
+    };
+
+    if let Some(flush_mask) = suit_masks.iter().find(|m| m.count_ones() >= 5) {
+        if let Some(high) = straight_high(*flush_mask) {
+            return value(8, &[high]);
+        }
+    }
+
+    let quads = ranks_with(4);
+    if let Some(&quad) = quads.first() {
+        return value(7, &[quad, kickers(&[quad])[0]]);
+    }
+
+    let trips = ranks_with(3);
+    let pairs = ranks_with(2);
+    if let Some(&trip) = trips.first() {
+        let pair = trips.get(1).into_iter().chain(pairs.first()).max();
+        if let Some(&pair) = pair {
+            return value(6, &[trip, pair]);
+        }
+    }
+
+    if let Some(flush_mask) = suit_masks.iter().find(|m| m.count_ones() >= 5) {
+        let flush = (0..13)
+            .rev()
+            .filter(|&r| flush_mask >> r & 1 == 1)
+            .collect::<Vec<_>>();
+        return value(5, &flush);
+    }
+
+    if let Some(high) = straight_high(mask) {
+        return value(4, &[high]);
+    }
+
+    if let Some(&trip) = trips.first() {
+        let mut ranks = vec![trip];
+        ranks.extend(kickers(&[trip]).into_iter().take(2));
+        return value(3, &ranks);
+    }
+
+    if pairs.len() >= 2 {
+        let mut ranks = vec![pairs[0], pairs[1]];
+        ranks.extend(kickers(&ranks).into_iter().take(1));
+        return value(2, &ranks);
+    }
+
+    if let Some(&pair) = pairs.first() {
+        let mut ranks = vec![pair];
+        ranks.extend(kickers(&[pair]).into_iter().take(3));
+        return value(1, &ranks);
+    }
+
+    value(0, &kickers(&[]))
+}
+
+/// Number of ranks that complete a straight using at least one private card.
+fn straight_outs(hole_mask: u16, board_mask: u16) -> usize {
+    (0..13)
+        .filter(|&r| {
+            let out = 1 << r;
+            (hole_mask | board_mask) & out == 0
+                && has_straight(hole_mask | board_mask | out)
+                && !has_straight(board_mask | out)
+        })
+        .count()
+}
+
+fn has_backdoor_straight(hole_mask: u16, board_mask: u16) -> bool {
+    (0..13).any(|r1| {
+        (r1 + 1..13).any(|r2| {
+            let outs = 1 << r1 | 1 << r2;
+            (hole_mask | board_mask) & outs == 0
+                && has_straight(hole_mask | board_mask | outs)
+                && !has_straight(board_mask | outs)
+        })
+    })
+}
+
+/// Classifies the private hand `hand` on `board` (3 to 5 cards).
+pub fn classify(hand: (Card, Card), board: &[Card]) -> (MadeHand, Draw) {
+    let hole = [hand.0, hand.1];
+    let cards = || hole.iter().chain(board.iter()).copied();
+
+    let mut rank_count = [0; 13];
+    let mut board_rank_count = [0; 13];
+    let mut suit_count = [0; 4];
+    for card in cards() {
+        rank_count[rank(card)] += 1;
+        suit_count[suit(card)] += 1;
+    }
+    for &card in board {
+        board_rank_count[rank(card)] += 1;
+    }
+
+    let hole_mask = rank_mask(hole.iter().copied());
+    let board_mask = rank_mask(board.iter().copied());
+    let flush_suit = (0..4).find(|&s| suit_count[s] >= 5);
+
+    let made = if flush_suit.map_or(false, |s| {
+        has_straight(rank_mask(cards().filter(|&c| suit(c) == s)))
+    }) {
+        MadeHand::StraightFlush
+    } else if rank_count.contains(&4) {
+        MadeHand::Quads
+    } else if rank_count.iter().filter(|&&c| c >= 3).count() >= 2
+        || (rank_count.contains(&3) && rank_count.contains(&2))
+    {
+        MadeHand::FullHouse
+    } else if flush_suit.is_some() {
+        MadeHand::Flush
+    } else if has_straight(hole_mask | board_mask) {
+        MadeHand::Straight
+    } else {
+        classify_pairs(hole, &rank_count, &board_rank_count)
+    };
+
+    let draw = if board.len() >= 5 || made <= MadeHand::Flush {
+        Draw::NoDraw
+    } else {
+        let hole_suits = [suit(hole[0]), suit(hole[1])];
+        let flush_draw = hole_suits.iter().any(|&s| suit_count[s] == 4);
+        let backdoor_flush = board.len() == 3 && hole_suits.iter().any(|&s| suit_count[s] == 3);
+        let outs = match made {
+            MadeHand::Straight => 0,
+            _ => straight_outs(hole_mask, board_mask),
+        };
+
+        if flush_draw && outs > 0 {
+            Draw::ComboDraw
+        } else if flush_draw {
+            Draw::FlushDraw
+        } else if outs >= 2 {
+            Draw::Oesd
+        } else if outs == 1 {
+            Draw::Gutshot
+        } else if backdoor_flush {
+            Draw::BackdoorFlushDraw
+        } else if board.len() == 3
+            && made != MadeHand::Straight
+            && has_backdoor_straight(hole_mask, board_mask)
+        {
+            Draw::BackdoorStraightDraw
+        } else {
+            Draw::NoDraw
+        }
+    };
+
+    (made, draw)
+}
+
+fn classify_pairs(
+    hole: [Card; 2],
+    rank_count: &[i32; 13],
+    board_rank_count: &[i32; 13],
+) -> MadeHand {
+    let (r1, r2) = (
+        rank(hole[0]).max(rank(hole[1])),
+        rank(hole[0]).min(rank(hole[1])),
+    );
+
+    if rank_count.contains(&3) {
+        if r1 == r2 && board_rank_count[r1] == 1 {
+            return MadeHand::Set;
+        }
+        if board_rank_count[r1] == 2 || board_rank_count[r2] == 2 {
+            return MadeHand::Trips;
+        }
+    }
+
+    if r1 != r2 && board_rank_count[r1] > 0 && board_rank_count[r2] > 0 {
+        return MadeHand::TwoPair;
+    }
+
+    // distinct board ranks in descending order
+    let board_ranks = (0..13)
+        .rev()
+        .filter(|&r| board_rank_count[r] > 0)
+        .collect::<Vec<_>>();
+
+    if r1 == r2 {
+        return match board_ranks.iter().position(|&r| r < r1) {
+            Some(0) => MadeHand::Overpair,
+            Some(1) => MadeHand::MiddlePair,
+            Some(_) => MadeHand::BottomPair,
+            None => MadeHand::Underpair,
+        };
+    }
+
+    let (pair, kicker) = match (board_rank_count[r1] > 0, board_rank_count[r2] > 0) {
+        (true, _) => (r1, r2),
+        (_, true) => (r2, r1),
+        _ => return MadeHand::HighCard,
+    };
+
+    match board_ranks.iter().position(|&r| r == pair) {
+        Some(0) => {
+            let better_kickers = (kicker + 1..13)
+                .filter(|&r| r != pair && board_rank_count[r] == 0)
+                .count();
+            match better_kickers {
+                0 => MadeHand::TopPairTopKicker,
+                1 | 2 => MadeHand::TopPairGoodKicker,
+                _ => MadeHand::TopPairWeakKicker,
+            }
+        }
+        Some(1) => MadeHand::MiddlePair,
+        _ => MadeHand::BottomPair,
+    }
+}
+
+#[derive(Serialize)]
+pub struct CategoryStats<T> {
+    pub category: T,
+    /// Sum of the weights.
+    pub combos: f64,
+    /// Share of the range in [0, 1].
+    pub weight: f64,
+    pub strategy: Vec<f64>,
+    /// Empty when either range is empty.
+    pub action_ev: Vec<f64>,
+}
+
+#[derive(Serialize)]
+pub struct HandCategoryReport {
+    pub player: String,
+    pub actions: Vec<String>,
+    pub made: Vec<CategoryStats<MadeHand>>,
+    pub draws: Vec<CategoryStats<Draw>>,
+}
+
+fn aggregate<T: Copy + Ord>(
+    categories: &[T],
+    weights: &[f32],
+    normalized: &[f32],
+    strategy: &[f32],
+    action_ev: &[f32],
+) -> Vec<CategoryStats<T>> {
+    let num_hands = categories.len();
+    if num_hands == 0 {
+        return Vec::new();
+    }
+
+    let num_actions = strategy.len() / num_hands;
+    let total = normalized.iter().map(|&w| w as f64).sum::<f64>();
+
+    let mut sorted = categories.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+
+    sorted
+        .into_iter()
+        .filter_map(|category| {
+            let hands = (0..num_hands)
+                .filter(|&hand| categories[hand] == category && weights[hand] > 0.0)
+                .collect::<Vec<_>>();
+            if hands.is_empty() {
+                return None;
+            }
+
+            let pick = |values: &[f32]| hands.iter().map(|&h| values[h]).collect::<Vec<_>>();
+            let hand_weights = pick(normalized);
+            let per_action = |values: &[f32]| match values.is_empty() {
+                true => Vec::new(),
+                false => (0..num_actions)
+                    .map(|action| {
+                        let values = &values[action * num_hands..(action + 1) * num_hands];
+                        weighted_average(&pick(values), &hand_weights)
+                    })
+                    .collect(),
+            };
+
+            Some(CategoryStats {
+                category,
+                combos: hands.iter().map(|&h| weights[h] as f64).sum(),
+                weight: hand_weights.iter().map(|&w| w as f64).sum::<f64>() / total,
+                strategy: per_action(strategy),
+                action_ev: per_action(action_ev),
+            })
+        })
+        .collect()
+}
+
+/// Aggregates the strategy of the acting player at the current node per hand category.
+pub fn hand_category_report(game: &mut PostFlopGame) -> Result<HandCategoryReport, Error> {
+    ensure_solved(game)?;
+
+    if game.is_terminal_node() || game.is_chance_node() {
+        return Err(Error::invalid_field("history", "Not a decision node"));
+    }
+
+    let player = game.current_player();
+    let board = game.current_board();
+    let is_empty = |player: usize| game.weights(player).iter().all(|&w| w == 0.0);
+    let has_results = !is_empty(0) && !is_empty(1);
+
+    let weights = game.weights(player).to_vec();
+    let (normalized, action_ev) = match has_results {
+        true => {
+            game.cache_normalized_weights();
+            (
+                game.normalized_weights(player).to_vec(),
+                game.expected_values_detail(player),
+            )
+        }
+        false => (weights.clone(), Vec::new()),
+    };
+    let strategy = game.strategy();
+
+    let (made, draws): (Vec<_>, Vec<_>) = game
+        .private_cards(player)
+        .iter()
+        .map(|&hand| classify(hand, &board))
+        .unzip();
+
+    Ok(HandCategoryReport {
+        player: current_player(game),
+        actions: game
+            .available_actions()
+            .into_iter()
+            .map(action_to_string)
+            .collect(),
+        made: aggregate(&made, &weights, &normalized, &strategy, &action_ev),
+        draws: aggregate(&draws, &weights, &normalized, &strategy, &action_ev),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::range::cards;
+
+    fn classify_str(hand: &str, board: &str) -> (MadeHand, Draw) {
+        let hand = cards(hand);
+        classify((hand[0], hand[1]), &cards(board))
+    }
+
+    #[test]
+    fn wheel() {
+        let wheel = evaluate(&cards("Ah2c3d4s5hKdQc"));
+        assert_eq!(wheel >> 20, 4);
+        assert!(wheel < evaluate(&cards("2c3d4s5h6cKdQc")));
+        assert!(wheel > evaluate(&cards("AhAc3d4s8hKdQc")));
+        assert_eq!(
+            straight_high(rank_mask(cards("Ah2c3d4s5h").into_iter())),
+            Some(3)
+        );
+        assert_eq!(
+            classify_str("Ah2c", "3d4s5h"),
+            (MadeHand::Straight, Draw::NoDraw)
+        );
+    }
+
+    #[test]
+    fn straight_flush_beats_flush() {
+        let straight_flush = evaluate(&cards("9hThJhQhKh2c3d"));
+        let flush = evaluate(&cards("Ah2h4h6h8h9cTd"));
+        assert_eq!(straight_flush >> 20, 8);
+        assert_eq!(flush >> 20, 5);
+        assert!(straight_flush > flush);
+
+        // a straight and a flush that do not share five cards
+        assert_eq!(evaluate(&cards("9h8c5h6h7hKh2c")) >> 20, 5);
+        assert_eq!(classify_str("9h8c", "5h6h7hKh2c").0, MadeHand::Flush);
+        assert_eq!(
+            classify_str("9h8h", "5h6h7hKh2c").0,
+            MadeHand::StraightFlush
+        );
+    }
+
+    #[test]
+    fn full_house_from_two_trips() {
+        let two_trips = evaluate(&cards("KsKhKd7s7h7d2c"));
+        assert_eq!(two_trips >> 20, 6);
+        assert_eq!(two_trips, evaluate(&cards("KsKhKd7s7h2c3d")));
+        assert!(two_trips < evaluate(&cards("KsKhKd8s8h2c3d")));
+        assert_eq!(classify_str("Kc7c", "KsKh7s7h2d").0, MadeHand::FullHouse);
+    }
+
+    #[test]
+    fn set_and_trips() {
+        assert_eq!(classify_str("7c7d", "7hKs2d").0, MadeHand::Set);
+        assert_eq!(classify_str("Ac7c", "7h7s2d").0, MadeHand::Trips);
+        assert_eq!(evaluate(&cards("7c7d7hKs2d")) >> 20, 3);
+    }
+
+    #[test]
+    fn pocket_pairs() {
+        assert_eq!(classify_str("QcQd", "Js8h3c").0, MadeHand::Overpair);
+        assert_eq!(classify_str("TcTd", "Js8h3c").0, MadeHand::MiddlePair);
+        assert_eq!(classify_str("4c4d", "Js8h6c").0, MadeHand::Underpair);
+    }
+
+    #[test]
+    fn top_pair_kickers() {
+        assert_eq!(classify_str("AcKd", "Ks8h3c").0, MadeHand::TopPairTopKicker);
+        assert_eq!(classify_str("AcKd", "As8h3c").0, MadeHand::TopPairTopKicker);
+        assert_eq!(
+            classify_str("KcJd", "Ks8h3c").0,
+            MadeHand::TopPairGoodKicker
+        );
+        assert_eq!(
+            classify_str("Kc4d", "Ks8h3c").0,
+            MadeHand::TopPairWeakKicker
+        );
+    }
+
+    #[test]
+    fn straight_draws() {
+        let mask = |s: &str| rank_mask(cards(s).into_iter());
+        assert_eq!(straight_outs(mask("9c8d"), mask("7h6s2c")), 2);
+        assert_eq!(straight_outs(mask("9c8d"), mask("6h5s2c")), 1);
+        assert_eq!(classify_str("9c8d", "7h6s2c").1, Draw::Oesd);
+        assert_eq!(classify_str("9c8d", "6h5s2c").1, Draw::Gutshot);
+
+        // double gutshot
+        assert_eq!(classify_str("9c7d", "Jh5s8c").1, Draw::Oesd);
+    }
+
+    #[test]
+    fn flush_and_combo_draws() {
+        assert_eq!(classify_str("AhKh", "7h6s2h").1, Draw::FlushDraw);
+        assert_eq!(classify_str("9h8h", "7h6s2h").1, Draw::ComboDraw);
+        assert_eq!(classify_str("9h8h", "6h5s2h").1, Draw::ComboDraw);
+        // a made straight with a flush draw
+        assert_eq!(classify_str("9h8h", "7h6s2h5c").1, Draw::FlushDraw);
+
+        // no draws on the river
+        assert_eq!(classify_str("9h8h", "7h6s2hKcKd").1, Draw::NoDraw);
+    }
+}
@@ -0,0 +1,185 @@
+use crate::error::*;
+use crate::solver::*;
+use postflop_solver::*;
+use serde::{Deserialize, Serialize};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Instant;
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct SolveOptions {
+    /// Target exploitability in chips (not a percentage of the pot).
+    pub target_exploitability: f32,
+    pub max_iterations: u32,
+    /// Time limit in seconds, excluding paused time.
+    #[serde(default)]
+    pub time_limit: Option<f64>,
+    /// Exploitability is computed every `exploitability_interval` iterations.
+    #[serde(default = "default_exploitability_interval")]
+    pub exploitability_interval: u32,
+}
+
+fn default_exploitability_interval() -> u32 {
+    10
+}
+
+impl Default for SolveOptions {
+    fn default() -> Self {
+        Self {
+            target_exploitability: 0.0,
+            max_iterations: 1000,
+            time_limit: None,
+            exploitability_interval: default_exploitability_interval(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SolveStatus {
+    #[default]
+    Idle,
+    Running,
+    Paused,
+    Finished,
+    Cancelled,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct SolveProgress {
+    pub status: SolveStatus,
+    pub iteration: u32,
+    pub exploitability: f32,
+    /// Elapsed time in seconds, excluding paused time.
+    pub elapsed: f64,
+    #[serde(skip)]
+    exploitability_iteration: Option<u32>,
+}
+
+/// State of the background solve loop, shared between the solving thread and the commands.
+#[derive(Default)]
+pub struct SolveJob {
+    pub options: SolveOptions,
+    pub progress: SolveProgress,
+    /// `Paused` or `Cancelled` when the solving thread is requested to stop.
+    pub request: Option<SolveStatus>,
+}
+
+impl SolveJob {
+    /// Resets the job for a new solve; the caller is expected to spawn `run_solve` afterwards.
+    pub fn start(&mut self, options: SolveOptions) {
+        self.options = options;
+        self.progress = SolveProgress {
+            status: SolveStatus::Running,
+            ..Default::default()
+        };
+        self.request = None;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.progress.status == SolveStatus::Running
+    }
+}
+
+/// Locks `job`, failing if a solve is running or paused. Commands that modify the game keep the
+/// guard until they are done, so that no solve can start in the meantime.
+pub fn lock_idle(job: &Mutex<SolveJob>) -> Result<MutexGuard<'_, SolveJob>, Error> {
+    let job = job.lock().unwrap();
+    match job.progress.status {
+        SolveStatus::Running | SolveStatus::Paused => Err(Error::solver_state(
+            "Solver is running or paused; cancel it first",
+        )),
+        _ => Ok(job),
+    }
+}
+
+/// Runs solver iterations on `game` until the target exploitability, the iteration limit or the
+/// time limit is reached, or until the job is requested to pause or cancel.
+///
+/// Finalizes the game unless paused. `on_progress` is called whenever the exploitability is
+/// recomputed and when the loop stops. `game` is locked for each iteration only, so that other
+/// commands can read it between iterations. This function is computationally heavy; call it
+/// inside a thread pool.
+///
+/// Fails without running any iteration if `game` is not allocated or already solved; the job is
+/// then reset to `Idle`.
+pub fn run_solve(
+    game: &Mutex<PostFlopGame>,
+    job: &Mutex<SolveJob>,
+    mut on_progress: impl FnMut(&SolveProgress),
+) -> Result<(), Error> {
+    if let Err(e) = ensure_solvable(&game.lock().unwrap()) {
+        let progress = {
+            let mut job = job.lock().unwrap();
+            job.progress.status = SolveStatus::Idle;
+            job.request = None;
+            job.progress
+        };
+        on_progress(&progress);
+        return Err(e);
+    }
+
+    let (options, mut progress) = {
+        let job = job.lock().unwrap();
+        (job.options, job.progress)
+    };
+
+    let start = Instant::now();
+    let elapsed_base = progress.elapsed;
+    let interval = options.exploitability_interval.max(1);
+
+    let mut update = |progress: &mut SolveProgress, exploitability: bool| {
+        if exploitability {
+            progress.exploitability = compute_exploitability(&*game.lock().unwrap()).max(0.0);
+            progress.exploitability_iteration = Some(progress.iteration);
+        }
+        progress.elapsed = elapsed_base + start.elapsed().as_secs_f64();
+        job.lock().unwrap().progress = *progress;
+        on_progress(progress);
+    };
+
+    if progress.exploitability_iteration.is_none() {
+        update(&mut progress, true);
+    }
+
+    let status = loop {
+        let elapsed = elapsed_base + start.elapsed().as_secs_f64();
+        if progress.iteration >= options.max_iterations
+            || progress.exploitability <= options.target_exploitability
+            || options.time_limit.map_or(false, |limit| elapsed >= limit)
+        {
+            break SolveStatus::Finished;
+        }
+
+        let request = job.lock().unwrap().request.take();
+        match request {
+            Some(SolveStatus::Paused) => {
+                progress.status = SolveStatus::Paused;
+                update(&mut progress, false);
+                return Ok(());
+            }
+            Some(SolveStatus::Cancelled) => break SolveStatus::Cancelled,
+            _ => {}
+        }
+
+        solve_step(&*game.lock().unwrap(), progress.iteration);
+        progress.iteration += 1;
+
+        if progress.iteration % interval == 0 {
+            update(&mut progress, true);
+        }
+    };
+
+    let stale = progress.exploitability_iteration != Some(progress.iteration);
+    if stale {
+        progress.exploitability = compute_exploitability(&*game.lock().unwrap()).max(0.0);
+        progress.exploitability_iteration = Some(progress.iteration);
+    }
+
+    finalize(&mut *game.lock().unwrap());
+
+    progress.status = status;
+    progress.elapsed = elapsed_base + start.elapsed().as_secs_f64();
+    job.lock().unwrap().progress = progress;
+    on_progress(&progress);
+    Ok(())
+}
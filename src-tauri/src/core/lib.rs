@@ -0,0 +1,17 @@
+pub mod bunching;
+pub mod config;
+pub mod dump;
+pub mod equity;
+pub mod error;
+pub mod export;
+pub mod format;
+pub mod hand;
+pub mod job;
+pub mod line;
+pub mod locking;
+pub mod pattern;
+pub mod range;
+pub mod session;
+pub mod solver;
+pub mod subgame;
+pub mod tree;
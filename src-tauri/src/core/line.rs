@@ -0,0 +1,82 @@
+use crate::error::*;
+use postflop_solver::*;
+
+#[inline]
+pub fn action_to_string(action: Action) -> String {
+    match action {
+        Action::Fold => "Fold:0".to_string(),
+        Action::Check => "Check:0".to_string(),
+        Action::Call => "Call:0".to_string(),
+        Action::Bet(amount) => format!("Bet:{amount}"),
+        Action::Raise(amount) => format!("Raise:{amount}"),
+        Action::AllIn(amount) => format!("Allin:{amount}"),
+        _ => unreachable!(),
+    }
+}
+
+#[inline]
+pub fn encode_action(action: Action) -> String {
+    match action {
+        Action::Fold => "F".to_string(),
+        Action::Check => "X".to_string(),
+        Action::Call => "C".to_string(),
+        Action::Bet(amount) => format!("B{amount}"),
+        Action::Raise(amount) => format!("R{amount}"),
+        Action::AllIn(amount) => format!("A{amount}"),
+        _ => unreachable!(),
+    }
+}
+
+pub fn encode_line(line: &[Action]) -> String {
+    let mut flag = 0;
+    let mut encoded = String::new();
+
+    if line.is_empty() {
+        return "(Root)".to_string();
+    }
+
+    for &action in line {
+        if !encoded.is_empty() {
+            let delimiter = if flag == 2 { "|" } else { "-" };
+            flag = if flag == 2 { 0 } else { flag };
+            encoded.push_str(delimiter);
+        }
+        match action {
+            Action::Check => flag += 1,
+            Action::Call => flag = 2,
+            _ => flag = 0,
+        }
+        encoded.push_str(&encode_action(action));
+    }
+
+    encoded
+}
+
+pub fn decode_action(action: &str) -> Result<Action, Error> {
+    let invalid = || Error::invalid_line(action, "Invalid action");
+    match action {
+        "F" => Ok(Action::Fold),
+        "X" => Ok(Action::Check),
+        "C" => Ok(Action::Call),
+        _ => {
+            let mut chars = action.chars();
+            let first_char = chars.next().ok_or_else(invalid)?;
+            let amount = chars.as_str().parse().map_err(|_| invalid())?;
+            match first_char {
+                'B' => Ok(Action::Bet(amount)),
+                'R' => Ok(Action::Raise(amount)),
+                'A' => Ok(Action::AllIn(amount)),
+                _ => Err(invalid()),
+            }
+        }
+    }
+}
+
+pub fn decode_line(line: &str) -> Result<Vec<Action>, Error> {
+    line.split(&['-', '|'][..])
+        .map(|action| {
+            decode_action(action)
+                .map_err(|_| Error::invalid_line(line, format!("Invalid action `{action}`")))
+        })
+        .collect()
+}
@@ -0,0 +1,153 @@
+use crate::error::*;
+use crate::solver::*;
+use postflop_solver::*;
+use serde::Serialize;
+
+/// Strategy locked at the node reached by `history` (the indices used by `apply_history`).
+#[derive(Clone, Serialize)]
+pub struct NodeLock {
+    pub history: Vec<usize>,
+    pub player: usize,
+    pub num_actions: usize,
+    pub strategy: Vec<f32>,
+}
+
+#[inline]
+fn can_apply(game: &PostFlopGame) -> bool {
+    // locks are applied to the storage only before solving; once the game is finalized, they
+    // take effect after the memory is allocated again
+    game.is_ready() && !game.is_solved()
+}
+
+/// Runs `f` at the decision node reached by `history`, then restores the current node. Fails with
+/// `InvalidField` if a step of `history` is not available.
+fn at_node<T>(
+    game: &mut PostFlopGame,
+    history: &[usize],
+    f: impl FnOnce(&mut PostFlopGame) -> Result<T, Error>,
+) -> Result<T, Error> {
+    ensure_allocated(game)?;
+    let current = game.history().to_vec();
+    apply_history_checked(game, history, "history")?;
+    let result = match game.is_terminal_node() || game.is_chance_node() {
+        true => Err(Error::invalid_field("history", "Not a decision node")),
+        false => f(game),
+    };
+    game.apply_history(&current);
+    result
+}
+
+/// Locks the strategy at the node reached by `history`. `strategy` has the same layout as
+/// `PostFlopGame::strategy` (action-major, one entry per private hand).
+pub fn lock_node(
+    game: &mut PostFlopGame,
+    locks: &mut Vec<NodeLock>,
+    history: &[usize],
+    strategy: Vec<f32>,
+) -> Result<(), Error> {
+    at_node(game, history, |game| {
+        let player = game.current_player();
+        let num_actions = game.available_actions().len();
+        let num_hands = game.private_cards(player).len();
+
+        if strategy.len() != num_actions * num_hands {
+            let message = format!("Strategy must have {} entries", num_actions * num_hands);
+            return Err(Error::invalid_field("strategy", message));
+        }
+        if strategy.iter().any(|&x| x < 0.0 || x.is_nan()) {
+            return Err(Error::invalid_field(
+                "strategy",
+                "Strategy must be non-negative",
+            ));
+        }
+
+        if can_apply(game) {
+            game.lock_current_strategy(&strategy);
+        }
+
+        let lock = NodeLock {
+            history: history.to_vec(),
+            player,
+            num_actions,
+            strategy,
+        };
+
+        match locks.iter_mut().find(|l| l.history == history) {
+            Some(existing) => *existing = lock,
+            None => locks.push(lock),
+        }
+
+        Ok(())
+    })
+}
+
+/// Locks the action frequencies of the given hands at the node reached by `history`. The other
+/// hands keep their locked strategy if the node is already locked, or the current strategy
+/// otherwise.
+pub fn lock_hands(
+    game: &mut PostFlopGame,
+    locks: &mut Vec<NodeLock>,
+    history: &[usize],
+    hands: &[usize],
+    frequencies: &[f32],
+) -> Result<(), Error> {
+    let mut strategy = at_node(game, history, |game| {
+        let num_actions = game.available_actions().len();
+        let num_hands = game.private_cards(game.current_player()).len();
+
+        if frequencies.len() != num_actions {
+            let message = format!("Frequencies must have {num_actions} entries");
+            return Err(Error::invalid_field("frequencies", message));
+        }
+        if hands.iter().any(|&hand| hand >= num_hands) {
+            return Err(Error::invalid_field("hands", "Invalid hand index"));
+        }
+
+        Ok(match locks.iter().find(|l| l.history == history) {
+            Some(lock) => lock.strategy.clone(),
+            None => game.strategy(),
+        })
+    })?;
+
+    let num_hands = strategy.len() / frequencies.len();
+    for &hand in hands {
+        for (action, &frequency) in frequencies.iter().enumerate() {
+            strategy[action * num_hands + hand] = frequency;
+        }
+    }
+
+    lock_node(game, locks, history, strategy)
+}
+
+pub fn unlock_node(
+    game: &mut PostFlopGame,
+    locks: &mut Vec<NodeLock>,
+    history: &[usize],
+) -> Result<(), Error> {
+    let index = locks
+        .iter()
+        .position(|l| l.history == history)
+        .ok_or_else(|| Error::invalid_field("history", "Node is not locked"))?;
+
+    at_node(game, history, |game| {
+        if can_apply(game) {
+            game.unlock_current_strategy();
+        }
+        Ok(())
+    })?;
+
+    locks.remove(index);
+    Ok(())
+}
+
+/// Applies `locks` to a game whose memory has just been allocated.
+pub fn apply_locks(game: &mut PostFlopGame, locks: &[NodeLock]) -> Result<(), Error> {
+    for lock in locks {
+        at_node(game, &lock.history, |game| {
+            game.lock_current_strategy(&lock.strategy);
+            Ok(())
+        })?;
+    }
+    game.back_to_root();
+    Ok(())
+}
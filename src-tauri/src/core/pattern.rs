@@ -0,0 +1,383 @@
+use crate::error::*;
+use crate::line::*;
+use postflop_solver::*;
+use serde::{Deserialize, Serialize};
+
+/// Element of a line: an action or the end of a street.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LineToken {
+    Action(Action),
+    StreetEnd,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PatternToken {
+    /// Exactly this action, e.g. `B10`.
+    Exact(Action),
+    /// Any bet, raise or all-in: `B*`, `R*` or `A*`.
+    Kind(char),
+    /// Any single action: `?`.
+    Any,
+    /// Any number of actions on the same street: `*`.
+    Star,
+    /// Any number of actions on any streets: `**`.
+    AnyStreets,
+    /// End of a street: `|`.
+    StreetEnd,
+}
+
+impl PatternToken {
+    fn accepts(self, token: LineToken) -> bool {
+        match (self, token) {
+            (PatternToken::StreetEnd, LineToken::StreetEnd) => true,
+            (PatternToken::Any, LineToken::Action(_)) => true,
+            (PatternToken::Exact(a), LineToken::Action(b)) => a == b,
+            (PatternToken::Kind(kind), LineToken::Action(action)) => matches!(
+                (kind, action),
+                ('B', Action::Bet(_)) | ('R', Action::Raise(_)) | ('A', Action::AllIn(_))
+            ),
+            _ => false,
+        }
+    }
+}
+
+/// Pattern over lines in the `-`/`|` notation of `encode_line`, e.g. `*-C|` (any flop line
+/// ending in a call, i.e. the first turn node) or `X-X|*` (every turn node after check-check).
+///
+/// Tokens are separated by `-` within a street and by `|` at the end of a street. A trailing `|`
+/// matches the first node of the next street. Besides actions such as `X` and `B10`, a token can
+/// be `?` (any action), `B*`/`R*`/`A*` (any bet, raise or all-in), `*` (any number of actions on
+/// the same street) or `**` (any number of actions on any streets). `(Root)` matches the root.
+#[derive(Clone, Debug)]
+pub struct LinePattern {
+    tokens: Vec<PatternToken>,
+}
+
+impl LinePattern {
+    pub fn parse(pattern: &str) -> Result<Self, Error> {
+        let pattern = pattern.trim();
+        if pattern.is_empty() || pattern == "(Root)" {
+            return Ok(Self { tokens: Vec::new() });
+        }
+
+        let invalid = |message: &str| Error::invalid_line(pattern, message);
+        let mut tokens = Vec::new();
+        let mut rest = pattern;
+
+        loop {
+            let end = rest.find(['-', '|']).unwrap_or(rest.len());
+            let (token, delimiter) = (&rest[..end], rest[end..].chars().next());
+
+            match token {
+                // only a trailing `|` may be followed by nothing
+                "" if delimiter.is_none() && tokens.last() == Some(&PatternToken::StreetEnd) => {}
+                "" => return Err(invalid("Empty token in pattern")),
+                "?" => tokens.push(PatternToken::Any),
+                "*" => tokens.push(PatternToken::Star),
+                "**" => tokens.push(PatternToken::AnyStreets),
+                "B*" | "R*" | "A*" => {
+                    tokens.push(PatternToken::Kind(token.chars().next().unwrap()))
+                }
+                _ => match decode_action(token) {
+                    Ok(action) => tokens.push(PatternToken::Exact(action)),
+                    Err(_) => return Err(invalid(&format!("Invalid token `{token}`"))),
+                },
+            }
+
+            match delimiter {
+                None => break,
+                Some(delimiter) => {
+                    if delimiter == '|' {
+                        tokens.push(PatternToken::StreetEnd);
+                    }
+                    rest = &rest[end + 1..];
+                }
+            }
+        }
+
+        Ok(Self { tokens })
+    }
+
+    /// Returns whether the node reached by `line` matches the pattern.
+    pub fn matches(&self, line: &[Action]) -> bool {
+        matches_tokens(&self.tokens, &line_tokens(line))
+    }
+}
+
+/// Splits `line` into streets in the same way as `encode_line`. A line ending with a call or a
+/// check-check ends with `StreetEnd`.
+fn line_tokens(line: &[Action]) -> Vec<LineToken> {
+    let mut tokens = Vec::new();
+    let mut prev = Action::None;
+    for &action in line {
+        tokens.push(LineToken::Action(action));
+        let street_end =
+            action == Action::Call || (action == Action::Check && prev == Action::Check);
+        if street_end {
+            tokens.push(LineToken::StreetEnd);
+        }
+        prev = if street_end { Action::None } else { action };
+    }
+    tokens
+}
+
+fn matches_tokens(pattern: &[PatternToken], line: &[LineToken]) -> bool {
+    match pattern.split_first() {
+        None => line.is_empty(),
+        Some((PatternToken::AnyStreets, rest)) => {
+            (0..=line.len()).any(|i| matches_tokens(rest, &line[i..]))
+        }
+        Some((PatternToken::Star, rest)) => {
+            let street_len = line
+                .iter()
+                .position(|&t| t == LineToken::StreetEnd)
+                .unwrap_or(line.len());
+            (0..=street_len).any(|i| matches_tokens(rest, &line[i..]))
+        }
+        Some((&token, rest)) => match line.split_first() {
+            Some((&first, line)) => token.accepts(first) && matches_tokens(rest, line),
+            None => false,
+        },
+    }
+}
+
+/// Action of a bulk edit, e.g. `B10`, or a bet or raise sized as a percentage of the pot after
+/// calling, e.g. `B33%` or `R100%`, like the sizes shown in the tree editor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EditAction {
+    Fixed(Action),
+    PotRelative { raise: bool, percent: f64 },
+}
+
+impl EditAction {
+    pub fn parse(action: &str) -> Result<Self, Error> {
+        let invalid = || Error::invalid_line(action, "Invalid action");
+        match action.strip_suffix('%') {
+            None => Ok(EditAction::Fixed(decode_action(action)?)),
+            Some(size) => {
+                let raise = match size.chars().next() {
+                    Some('B') => false,
+                    Some('R') => true,
+                    _ => return Err(invalid()),
+                };
+                let percent = size[1..].parse::<f64>().map_err(|_| invalid())?;
+                match percent > 0.0 && percent.is_finite() {
+                    true => Ok(EditAction::PotRelative { raise, percent }),
+                    false => Err(invalid()),
+                }
+            }
+        }
+    }
+
+    /// Returns the action at the current node of `tree`, where `street_start` is the amount each
+    /// player had put in before the current street.
+    fn action(self, tree: &ActionTree, street_start: i32) -> Action {
+        match self {
+            EditAction::Fixed(action) => action,
+            EditAction::PotRelative { raise, percent } => {
+                let amount = pot_relative_amount(
+                    tree.config().starting_pot,
+                    tree.total_bet_amount(),
+                    street_start,
+                    percent,
+                );
+                match raise {
+                    false => Action::Bet(amount),
+                    true => Action::Raise(amount),
+                }
+            }
+        }
+    }
+}
+
+/// Returns the amount put in on the current street by a bet or raise of `percent` of the pot
+/// after calling, where `total_bet_amount` is what each player has put in so far and
+/// `street_start` what each had put in before the current street.
+fn pot_relative_amount(
+    starting_pot: i32,
+    total_bet_amount: [i32; 2],
+    street_start: i32,
+    percent: f64,
+) -> i32 {
+    let max_bet = total_bet_amount.into_iter().max().unwrap();
+    let pot = starting_pot + 2 * max_bet;
+    (max_bet - street_start) + (pot as f64 * percent / 100.0).round() as i32
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EditOperation {
+    Add,
+    Remove,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct BulkEditResult {
+    /// Lines of the nodes that were edited.
+    pub affected: Vec<String>,
+    /// Lines of the matching nodes where the edit failed (e.g. the action already exists or
+    /// exceeds the stack), with the reason.
+    pub skipped: Vec<(String, String)>,
+}
+
+/// Collects the lines of the decision nodes matching `pattern`, with the amount each player had
+/// put in before the street of the node.
+fn matching_nodes(
+    tree: &mut ActionTree,
+    pattern: &LinePattern,
+    street_start: i32,
+    nodes: &mut Vec<(Vec<Action>, i32)>,
+) {
+    if tree.is_terminal_node() {
+        return;
+    }
+
+    // the chance node shares its line with the first decision node of the next street
+    let street_start = match tree.is_chance_node() {
+        true => tree.total_bet_amount()[0],
+        false => street_start,
+    };
+
+    let history = tree.history().to_vec();
+    if pattern.matches(&history) {
+        nodes.push((history.clone(), street_start));
+    }
+
+    for &action in tree.available_actions().to_vec().iter() {
+        tree.play(action).unwrap();
+        matching_nodes(tree, pattern, street_start, nodes);
+        tree.apply_history(&history).unwrap();
+    }
+}
+
+/// Adds or removes `action` at every decision node of `tree` whose line matches `pattern`. Nodes
+/// that no longer exist because of an earlier edit are ignored. The current node of `tree` is
+/// restored if it still exists.
+pub fn bulk_edit(
+    tree: &mut ActionTree,
+    pattern: &LinePattern,
+    operation: EditOperation,
+    action: EditAction,
+) -> BulkEditResult {
+    let history = tree.history().to_vec();
+    let mut nodes = Vec::new();
+    tree.back_to_root();
+    matching_nodes(tree, pattern, tree.total_bet_amount()[0], &mut nodes);
+
+    let mut result = BulkEditResult::default();
+    for (line, street_start) in nodes {
+        if tree.apply_history(&line).is_err() {
+            continue;
+        }
+
+        let action = action.action(tree, street_start);
+        let edited = match operation {
+            EditOperation::Add => tree.add_action(action),
+            EditOperation::Remove => tree.remove_action(action),
+        };
+        match edited {
+            Ok(()) => result.affected.push(encode_line(&line)),
+            Err(e) => result.skipped.push((encode_line(&line), e)),
+        }
+    }
+
+    if tree.apply_history(&history).is_err() {
+        tree.back_to_root();
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, line: &str) -> bool {
+        let line = match line {
+            "" => Vec::new(),
+            line => decode_line(line).unwrap(),
+        };
+        LinePattern::parse(pattern).unwrap().matches(&line)
+    }
+
+    #[test]
+    fn doc_examples() {
+        assert!(matches("*-C|", "B10-C"));
+        assert!(matches("*-C|", "X-B10-R30-C"));
+        assert!(!matches("*-C|", "B10"));
+        assert!(!matches("*-C|", "X-X|B10-C"));
+
+        assert!(matches("X-X|*", "X-X"));
+        assert!(matches("X-X|*", "X-X|B10"));
+        assert!(matches("X-X|*", "X-X|X-B10"));
+        assert!(!matches("X-X|*", "X-X|B10-C"));
+        assert!(!matches("X-X|*", "X-B10"));
+    }
+
+    #[test]
+    fn root() {
+        assert!(matches("(Root)", ""));
+        assert!(matches("", ""));
+        assert!(!matches("(Root)", "X"));
+        assert!(matches("**", ""));
+        assert!(matches("*", ""));
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(matches("B*-?", "B10-R30"));
+        assert!(matches("B*-?", "B10-F"));
+        assert!(!matches("B*-?", "X-B10"));
+        assert!(!matches("R*", "B10"));
+        assert!(matches("X-B*-A*", "X-B10-A100"));
+        assert!(matches("**|X", "X-X|B10-C|X"));
+        assert!(matches("**", "X-X|B10-C|X"));
+        assert!(!matches("**|X", "X-X|B10"));
+    }
+
+    #[test]
+    fn malformed() {
+        for pattern in ["X--X", "Q", "-X", "X-", "X||X", "|", "B10-*Z", "B"] {
+            assert!(
+                matches!(LinePattern::parse(pattern), Err(Error::InvalidLine { .. })),
+                "{pattern}"
+            );
+        }
+    }
+
+    #[test]
+    fn edit_actions() {
+        assert_eq!(
+            EditAction::parse("B10").unwrap(),
+            EditAction::Fixed(Action::Bet(10))
+        );
+        assert_eq!(
+            EditAction::parse("B33%").unwrap(),
+            EditAction::PotRelative {
+                raise: false,
+                percent: 33.0
+            }
+        );
+        assert_eq!(
+            EditAction::parse("R100%").unwrap(),
+            EditAction::PotRelative {
+                raise: true,
+                percent: 100.0
+            }
+        );
+        for action in ["X33%", "B%", "B0%", "B-10%", "Bx%"] {
+            assert!(EditAction::parse(action).is_err(), "{action}");
+        }
+    }
+
+    #[test]
+    fn pot_relative_sizing() {
+        // first to act on the flop
+        assert_eq!(pot_relative_amount(100, [0, 0], 0, 33.0), 33);
+        // pot-sized raise facing a bet of 50: call 50 into 200, then raise 200 more
+        assert_eq!(pot_relative_amount(100, [0, 50], 0, 100.0), 250);
+        // half-pot bet on the turn after a called bet of 50
+        assert_eq!(pot_relative_amount(100, [50, 50], 50, 50.0), 100);
+        // raise on the turn facing a bet of 40
+        assert_eq!(pot_relative_amount(100, [90, 50], 50, 50.0), 40 + 140);
+    }
+}
@@ -0,0 +1,428 @@
+use crate::error::*;
+use postflop_solver::*;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+
+/// Maximum number of undo steps kept per range.
+const HISTORY_LIMIT: usize = 100;
+
+#[derive(Default)]
+struct RangeHistory {
+    undo: VecDeque<Range>,
+    redo: Vec<Range>,
+}
+
+/// Number of range slots of `RangeManager`.
+pub const NUM_SLOTS: usize = 6;
+
+/// Returns an error unless `player` is a valid range slot.
+pub fn check_slot(player: usize) -> Result<(), Error> {
+    match player < NUM_SLOTS {
+        true => Ok(()),
+        false => Err(Error::InvalidRange {
+            message: format!("Invalid range slot: {player}"),
+        }),
+    }
+}
+
+/// Ranges edited in the UI: OOP and IP ranges followed by the four folded ranges used by the
+/// bunching effect. Changes made through `set` can be undone. Every method taking a slot returns
+/// an error if the slot is invalid.
+#[derive(Default)]
+pub struct RangeManager(pub [Range; NUM_SLOTS], [RangeHistory; NUM_SLOTS]);
+
+impl RangeManager {
+    /// Returns a copy of the range of `player`.
+    pub fn get(&self, player: usize) -> Result<Range, Error> {
+        check_slot(player)?;
+        Ok(self.0[player])
+    }
+
+    /// Replaces the range of `player`, recording the previous range for undo.
+    pub fn set(&mut self, player: usize, range: Range) -> Result<(), Error> {
+        let current = self.get(player)?;
+        if current.raw_data() == range.raw_data() {
+            return Ok(());
+        }
+
+        let history = &mut self.1[player];
+        if history.undo.len() == HISTORY_LIMIT {
+            history.undo.pop_front();
+        }
+        history.undo.push_back(current);
+        history.redo.clear();
+        self.0[player] = range;
+        Ok(())
+    }
+
+    /// Applies `f` to a copy of the range of `player` and stores the result with `set`.
+    pub fn modify(&mut self, player: usize, f: impl FnOnce(&mut Range)) -> Result<(), Error> {
+        let mut range = self.get(player)?;
+        f(&mut range);
+        self.set(player, range)
+    }
+
+    /// Restores the previous range of `player`. Returns false if there is nothing to undo.
+    pub fn undo(&mut self, player: usize) -> Result<bool, Error> {
+        check_slot(player)?;
+        let history = &mut self.1[player];
+        match history.undo.pop_back() {
+            Some(range) => {
+                history.redo.push(self.0[player]);
+                self.0[player] = range;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Reapplies the last undone change of `player`. Returns false if there is nothing to redo.
+    pub fn redo(&mut self, player: usize) -> Result<bool, Error> {
+        check_slot(player)?;
+        let history = &mut self.1[player];
+        match history.redo.pop() {
+            Some(range) => {
+                history.undo.push_back(self.0[player]);
+                self.0[player] = range;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Returns whether undo and redo are available for `player`.
+    pub fn history_state(&self, player: usize) -> Result<(bool, bool), Error> {
+        check_slot(player)?;
+        let history = &self.1[player];
+        Ok((!history.undo.is_empty(), !history.redo.is_empty()))
+    }
+}
+
+pub fn num_combos(range: &Range) -> f64 {
+    range.raw_data().iter().fold(0.0, |acc, &x| acc + x as f64)
+}
+
+/// Returns an error unless (`row`, `col`) is a cell of the 13x13 grid (1-indexed, as in
+/// `update_weight`) and `weight` is between 0 and 1.
+pub fn check_cell(row: u8, col: u8, weight: f32) -> Result<(), Error> {
+    let message = if !(1..=13).contains(&row) || !(1..=13).contains(&col) {
+        "Row and column must be between 1 and 13"
+    } else if !(0.0..=1.0).contains(&weight) {
+        "Weight must be between 0 and 1"
+    } else {
+        return Ok(());
+    };
+    Err(Error::InvalidRange {
+        message: message.to_string(),
+    })
+}
+
+/// Sets the weight of the hand class at (`row`, `col`) of the 13x13 grid (1-indexed). The cell
+/// must have been checked with `check_cell`.
+pub fn update_weight(range: &mut Range, row: u8, col: u8, weight: f32) {
+    let rank1 = 13 - row;
+    let rank2 = 13 - col;
+    match row.cmp(&col) {
+        Ordering::Equal => range.set_weight_pair(rank1, weight),
+        Ordering::Less => range.set_weight_suited(rank1, rank2, weight),
+        Ordering::Greater => range.set_weight_offsuit(rank1, rank2, weight),
+    }
+}
+
+/// Returns the weights of the 13x13 grid in row-major order.
+pub fn class_weights(range: &Range) -> Vec<f32> {
+    let mut weights = vec![0.0; 13 * 13];
+
+    for row in 0..13 {
+        for col in 0..13 {
+            let rank1 = 12 - row as u8;
+            let rank2 = 12 - col as u8;
+            weights[row * 13 + col] = match row.cmp(&col) {
+                Ordering::Equal => range.get_weight_pair(rank1),
+                Ordering::Less => range.get_weight_suited(rank1, rank2),
+                Ordering::Greater => range.get_weight_offsuit(rank1, rank2),
+            };
+        }
+    }
+
+    weights
+}
+
+/// Returns the combos of the hand class at (`row`, `col`) of the 13x13 grid (0-indexed, in the
+/// same layout as `class_weights`).
+pub fn class_combos(row: usize, col: usize) -> Vec<(Card, Card)> {
+    let high = 12 - row.min(col) as u8;
+    let low = 12 - row.max(col) as u8;
+    let mut combos = Vec::new();
+
+    for suit1 in 0..4 {
+        for suit2 in 0..4 {
+            let include = match row.cmp(&col) {
+                Ordering::Equal => suit1 < suit2,
+                Ordering::Less => suit1 == suit2,
+                Ordering::Greater => suit1 != suit2,
+            };
+            if include {
+                combos.push((4 * high + suit1, 4 * low + suit2));
+            }
+        }
+    }
+
+    combos
+}
+
+/// Returns the name of the hand class at `index` of the 13x13 grid (row-major), e.g. `"AKs"`.
+pub fn class_name(index: usize) -> String {
+    const RANKS: &[u8; 13] = b"23456789TJQKA";
+    let (row, col) = (index / 13, index % 13);
+    let high = RANKS[12 - row.min(col)] as char;
+    let low = RANKS[12 - row.max(col)] as char;
+    match row.cmp(&col) {
+        Ordering::Equal => format!("{high}{low}"),
+        Ordering::Less => format!("{high}{low}s"),
+        Ordering::Greater => format!("{high}{low}o"),
+    }
+}
+
+/// Returns whether every combo of each hand class has the same weight, in row-major order.
+pub fn suit_uniform_flags(range: &Range) -> Vec<bool> {
+    (0..13 * 13)
+        .map(|index| {
+            let mut weights = class_combos(index / 13, index % 13)
+                .into_iter()
+                .map(|(card1, card2)| range.get_weight_by_cards(card1, card2));
+            let first = weights.next().unwrap();
+            weights.all(|weight| weight == first)
+        })
+        .collect()
+}
+
+pub fn check_combo(card1: Card, card2: Card, weight: f32) -> Result<(), Error> {
+    let message = if card1 >= 52 || card2 >= 52 || card1 == card2 {
+        "Invalid combo"
+    } else if !(0.0..=1.0).contains(&weight) {
+        "Weight must be between 0 and 1"
+    } else {
+        return Ok(());
+    };
+    Err(Error::InvalidRange {
+        message: message.to_string(),
+    })
+}
+
+/// Returns the bit mask of `cards`, checking that they are valid and distinct.
+pub fn card_mask(cards: &[Card]) -> Result<u64, Error> {
+    let mut mask = 0u64;
+    for &card in cards {
+        if card >= 52 || mask & 1 << card != 0 {
+            return Err(Error::invalid_board("Duplicate or invalid card"));
+        }
+        mask |= 1 << card;
+    }
+    Ok(mask)
+}
+
+/// Parses a string of concatenated cards such as `"AsKd"`, for test fixtures.
+#[cfg(test)]
+pub(crate) fn cards(s: &str) -> Vec<Card> {
+    let s = s.as_bytes();
+    s.chunks(2)
+        .map(|c| {
+            let rank = b"23456789TJQKA".iter().position(|&r| r == c[0]).unwrap();
+            let suit = b"cdhs".iter().position(|&x| x == c[1]).unwrap();
+            (4 * rank + suit) as Card
+        })
+        .collect()
+}
+
+#[inline]
+fn is_live((card1, card2): (Card, Card), dead_mask: u64) -> bool {
+    dead_mask & (1 << card1 | 1 << card2) == 0
+}
+
+/// Same as `num_combos`, but ignores the combos that contain a card in `dead_mask`.
+pub fn live_num_combos(range: &Range, dead_mask: u64) -> f64 {
+    range
+        .raw_data()
+        .iter()
+        .enumerate()
+        .filter(|&(index, _)| is_live(index_to_card_pair(index), dead_mask))
+        .fold(0.0, |acc, (_, &x)| acc + x as f64)
+}
+
+/// Number of combos of each hand class that contain no card in `dead_mask`, in row-major order.
+pub fn live_class_combos(dead_mask: u64) -> Vec<u8> {
+    (0..13 * 13)
+        .map(|index| {
+            class_combos(index / 13, index % 13)
+                .into_iter()
+                .filter(|&combo| is_live(combo, dead_mask))
+                .count() as u8
+        })
+        .collect()
+}
+
+/// Same as `class_weights`, but averages the weights over the combos that contain no card in
+/// `dead_mask` (0 if there are none).
+pub fn live_class_weights(range: &Range, dead_mask: u64) -> Vec<f32> {
+    (0..13 * 13)
+        .map(|index| {
+            let weights = class_combos(index / 13, index % 13)
+                .into_iter()
+                .filter(|&combo| is_live(combo, dead_mask))
+                .map(|(card1, card2)| range.get_weight_by_cards(card1, card2))
+                .collect::<Vec<_>>();
+            match weights.is_empty() {
+                true => 0.0,
+                false => weights.iter().sum::<f32>() / weights.len() as f32,
+            }
+        })
+        .collect()
+}
+
+/// Combo-wise operation on two ranges. Results are clamped to [0, 1].
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum RangeOp {
+    /// Union.
+    Max,
+    /// Intersection.
+    Min,
+    Subtract,
+    Multiply,
+    /// `(1 - weight) * lhs + weight * rhs`.
+    WeightedAverage {
+        weight: f32,
+    },
+}
+
+/// Second operand of `RangeOp`: the index of a stored range or a range string.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum RangeOperand {
+    Slot(usize),
+    String(String),
+}
+
+impl RangeOperand {
+    pub fn resolve(&self, manager: &RangeManager) -> Result<Range, Error> {
+        match self {
+            RangeOperand::Slot(index) => manager.get(*index),
+            RangeOperand::String(s) => {
+                Range::from_sanitized_str(s).map_err(|message| Error::InvalidRange { message })
+            }
+        }
+    }
+}
+
+fn map_range(range: &Range, f: impl Fn(usize, f32) -> f32) -> Result<Range, Error> {
+    let data = range
+        .raw_data()
+        .iter()
+        .enumerate()
+        .map(|(index, &weight)| f(index, weight).clamp(0.0, 1.0))
+        .collect::<Vec<_>>();
+    Range::from_raw_data(&data).map_err(|message| Error::InvalidRange { message })
+}
+
+pub fn combine_ranges(lhs: &Range, rhs: &Range, op: RangeOp) -> Result<Range, Error> {
+    if let RangeOp::WeightedAverage { weight } = op {
+        if !(0.0..=1.0).contains(&weight) {
+            return Err(Error::invalid_field(
+                "weight",
+                "Weight must be between 0 and 1",
+            ));
+        }
+    }
+
+    let rhs = rhs.raw_data();
+    map_range(lhs, |index, a| {
+        let b = rhs[index];
+        match op {
+            RangeOp::Max => a.max(b),
+            RangeOp::Min => a.min(b),
+            RangeOp::Subtract => a - b,
+            RangeOp::Multiply => a * b,
+            RangeOp::WeightedAverage { weight } => (1.0 - weight) * a + weight * b,
+        }
+    })
+}
+
+pub fn scale_range(range: &Range, factor: f32) -> Result<Range, Error> {
+    if !(factor >= 0.0 && factor.is_finite()) {
+        return Err(Error::invalid_field(
+            "factor",
+            "Factor must be a non-negative number",
+        ));
+    }
+    map_range(range, |_, weight| weight * factor)
+}
+
+/// Clamps the nonzero weights to [`min`, `max`]. Combos not in the range stay excluded.
+pub fn clamp_range(range: &Range, min: f32, max: f32) -> Result<Range, Error> {
+    map_range(range, |_, weight| match weight > 0.0 {
+        true => weight.clamp(min, max),
+        false => 0.0,
+    })
+}
+
+#[derive(Serialize)]
+pub struct RangeDiff {
+    pub num_combos: [f64; 2],
+    /// Class weights of the second range minus those of the first, in row-major order. The weight
+    /// of a class is the average over its combos, so partially selected classes count too.
+    pub class_delta: Vec<f32>,
+    /// Sum of the absolute combo weight differences.
+    pub combo_difference: f64,
+    /// Weighted Jaccard index: sum of the combo-wise minimums over sum of the maximums (1 if
+    /// both ranges are empty).
+    pub overlap: f64,
+    /// Names of the classes that have weight in only one of the ranges.
+    pub only_in: [Vec<String>; 2],
+}
+
+pub fn compare_ranges(lhs: &Range, rhs: &Range) -> RangeDiff {
+    let (a, b) = (lhs.raw_data(), rhs.raw_data());
+
+    let mut combo_difference = 0.0;
+    let (mut min_sum, mut max_sum) = (0.0, 0.0);
+    for (&x, &y) in a.iter().zip(b) {
+        let (x, y) = (x as f64, y as f64);
+        combo_difference += (x - y).abs();
+        min_sum += x.min(y);
+        max_sum += x.max(y);
+    }
+
+    let has_weight = |range: &Range, index: usize| {
+        class_combos(index / 13, index % 13)
+            .into_iter()
+            .any(|(card1, card2)| range.get_weight_by_cards(card1, card2) > 0.0)
+    };
+
+    let mut only_in = [Vec::new(), Vec::new()];
+    for index in 0..13 * 13 {
+        match (has_weight(lhs, index), has_weight(rhs, index)) {
+            (true, false) => only_in[0].push(class_name(index)),
+            (false, true) => only_in[1].push(class_name(index)),
+            _ => {}
+        }
+    }
+
+    let class_delta = live_class_weights(rhs, 0)
+        .iter()
+        .zip(live_class_weights(lhs, 0))
+        .map(|(&y, x)| y - x)
+        .collect();
+
+    RangeDiff {
+        num_combos: [num_combos(lhs), num_combos(rhs)],
+        class_delta,
+        combo_difference,
+        overlap: match max_sum > 0.0 {
+            true => min_sum / max_sum,
+            false => 1.0,
+        },
+        only_in,
+    }
+}
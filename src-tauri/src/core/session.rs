@@ -0,0 +1,153 @@
+use crate::error::*;
+use crate::job::*;
+use crate::locking::*;
+use crate::range::*;
+use crate::tree::*;
+use postflop_solver::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Everything needed to set up, solve and browse one game.
+pub struct Session {
+    pub name: Mutex<String>,
+    pub ranges: Mutex<RangeManager>,
+    pub tree: Mutex<ActionTree>,
+    pub bunching: Mutex<Option<BunchingData>>,
+    pub game: Mutex<PostFlopGame>,
+    pub job: Mutex<SolveJob>,
+    pub locks: Mutex<Vec<NodeLock>>,
+    /// Memory allocated for the game in bytes (0 if not allocated). Cached so that it can be
+    /// reported while the game is locked by the solver.
+    memory_usage: AtomicU64,
+}
+
+impl Session {
+    pub fn new(name: String) -> Self {
+        Self {
+            name: Mutex::new(name),
+            ranges: Mutex::new(RangeManager::default()),
+            tree: Mutex::new(default_action_tree()),
+            bunching: Mutex::new(None),
+            game: Mutex::new(PostFlopGame::default()),
+            job: Mutex::new(SolveJob::default()),
+            locks: Mutex::new(Vec::new()),
+            memory_usage: AtomicU64::new(0),
+        }
+    }
+
+    pub fn memory_usage(&self) -> u64 {
+        self.memory_usage.load(Ordering::Relaxed)
+    }
+
+    /// Updates the cached memory usage from `game`, which must be the game of this session.
+    pub fn update_memory_usage(&self, game: &PostFlopGame) {
+        let memory_usage = if game.is_ready() || game.is_solved() {
+            let (uncompressed, compressed) = game.memory_usage();
+            match game.is_compression_enabled() {
+                false => uncompressed,
+                true => compressed,
+            }
+        } else {
+            0
+        };
+        self.memory_usage.store(memory_usage, Ordering::Relaxed);
+    }
+}
+
+#[derive(Serialize)]
+pub struct SessionInfo {
+    pub id: u32,
+    pub name: String,
+    pub is_current: bool,
+    pub memory_usage: u64,
+}
+
+pub struct SessionManager {
+    sessions: BTreeMap<u32, Arc<Session>>,
+    next_id: u32,
+    current: u32,
+}
+
+impl Default for SessionManager {
+    fn default() -> Self {
+        let mut sessions = BTreeMap::new();
+        sessions.insert(0, Arc::new(Session::new("Session 1".to_string())));
+        Self {
+            sessions,
+            next_id: 1,
+            current: 0,
+        }
+    }
+}
+
+impl SessionManager {
+    pub fn get(&self, id: u32) -> Result<Arc<Session>, Error> {
+        self.sessions
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| Error::invalid_session(id))
+    }
+
+    pub fn create(&mut self, name: Option<String>) -> u32 {
+        let id = self.next_id;
+        let name = name.unwrap_or_else(|| format!("Session {}", id + 1));
+        self.sessions.insert(id, Arc::new(Session::new(name)));
+        self.next_id += 1;
+        id
+    }
+
+    /// Removes the session. A running solver of the session is cancelled.
+    pub fn close(&mut self, id: u32) -> Result<(), Error> {
+        if self.sessions.len() == 1 && self.sessions.contains_key(&id) {
+            return Err(Error::InvalidSession {
+                session_id: id,
+                message: "Cannot close the last session".to_string(),
+            });
+        }
+
+        let session = self
+            .sessions
+            .remove(&id)
+            .ok_or_else(|| Error::invalid_session(id))?;
+        session.job.lock().unwrap().request = Some(SolveStatus::Cancelled);
+
+        if self.current == id {
+            self.current = *self.sessions.keys().next().unwrap();
+        }
+
+        Ok(())
+    }
+
+    pub fn current(&self) -> u32 {
+        self.current
+    }
+
+    pub fn switch(&mut self, id: u32) -> Result<(), Error> {
+        self.get(id)?;
+        self.current = id;
+        Ok(())
+    }
+
+    pub fn list(&self) -> Vec<SessionInfo> {
+        self.sessions
+            .iter()
+            .map(|(&id, session)| SessionInfo {
+                id,
+                name: session.name.lock().unwrap().clone(),
+                is_current: id == self.current,
+                memory_usage: session.memory_usage(),
+            })
+            .collect()
+    }
+
+    /// Total memory allocated for the games of all sessions in bytes.
+    pub fn memory_usage(&self) -> u64 {
+        self.sessions.values().map(|s| s.memory_usage()).sum()
+    }
+}
+
+pub fn get_session(sessions: &Mutex<SessionManager>, id: u32) -> Result<Arc<Session>, Error> {
+    sessions.lock().unwrap().get(id)
+}
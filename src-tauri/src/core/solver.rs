@@ -0,0 +1,552 @@
+use crate::config::*;
+use crate::error::*;
+use crate::line::*;
+use crate::tree::*;
+use postflop_solver::*;
+use serde::{Deserialize, Serialize};
+
+#[inline]
+pub fn action_usize(action: isize) -> usize {
+    match action {
+        -1 => usize::MAX,
+        a => a as usize,
+    }
+}
+
+#[inline]
+fn round(value: f64) -> f64 {
+    if value < 1.0 {
+        (value * 1000000.0).round() / 1000000.0
+    } else if value < 10.0 {
+        (value * 100000.0).round() / 100000.0
+    } else if value < 100.0 {
+        (value * 10000.0).round() / 10000.0
+    } else if value < 1000.0 {
+        (value * 1000.0).round() / 1000.0
+    } else if value < 10000.0 {
+        (value * 100.0).round() / 100.0
+    } else {
+        (value * 10.0).round() / 10.0
+    }
+}
+
+#[inline]
+fn round_iter<'a>(iter: impl Iterator<Item = &'a f32> + 'a) -> impl Iterator<Item = f64> + 'a {
+    iter.map(|&x| round(x as f64))
+}
+
+#[inline]
+pub fn weighted_average(slice: &[f32], weights: &[f32]) -> f64 {
+    let mut sum = 0.0;
+    let mut weight_sum = 0.0;
+    for (&value, &weight) in slice.iter().zip(weights.iter()) {
+        sum += value as f64 * weight as f64;
+        weight_sum += weight as f64;
+    }
+    sum / weight_sum
+}
+
+pub fn init_game(
+    game: &mut PostFlopGame,
+    ranges: &[Range],
+    board: &[u8],
+    params: &TreeConfigSpec,
+) -> Result<(), Error> {
+    let state = board_state(board.len())?;
+    let card_config = card_config(ranges, board)?;
+    let action_tree = params.action_tree(state)?;
+    Ok(game.update_config(card_config, action_tree)?)
+}
+
+/// Initializes `game` with a copy of the edited `tree`, rebuilt from its configuration and its
+/// added and removed lines without going through the line strings. `tree` itself is left intact
+/// for further editing.
+pub fn init_game_from_tree(
+    game: &mut PostFlopGame,
+    ranges: &[Range],
+    board: &[u8],
+    tree: &ActionTree,
+) -> Result<(), Error> {
+    let card_config = tree_card_config(tree, ranges, board)?;
+    Ok(game.update_config(card_config, rebuild_action_tree(tree)?)?)
+}
+
+/// Returns an error unless the memory of `game` has been allocated.
+pub fn ensure_allocated(game: &PostFlopGame) -> Result<(), Error> {
+    match game.is_ready() || game.is_solved() {
+        true => Ok(()),
+        false => Err(Error::not_initialized("Game is not initialized")),
+    }
+}
+
+/// Returns an error unless `game` can run solver iterations, i.e. its memory has been allocated
+/// and it has not been finalized yet.
+pub fn ensure_solvable(game: &PostFlopGame) -> Result<(), Error> {
+    ensure_allocated(game)?;
+    match game.is_solved() {
+        true => Err(Error::solver_state(
+            "Game is already solved; initialize it again to re-solve",
+        )),
+        false => Ok(()),
+    }
+}
+
+/// Returns an error unless `game` has been solved and finalized, which is required to read its
+/// equities and expected values.
+pub fn ensure_solved(game: &PostFlopGame) -> Result<(), Error> {
+    ensure_allocated(game)?;
+    match game.is_solved() {
+        true => Ok(()),
+        false => Err(Error::solver_state("Game is not solved yet")),
+    }
+}
+
+pub fn private_cards(game: &PostFlopGame) -> [Vec<u16>; 2] {
+    let convert = |player: usize| {
+        game.private_cards(player)
+            .iter()
+            .map(|&(c1, c2)| (c1 as u16) | (c2 as u16) << 8)
+            .collect()
+    };
+    [convert(0), convert(1)]
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct NodeRangeOptions {
+    /// Scales the weights so that the maximum weight is 1.
+    pub normalize: bool,
+    /// Weights below this value (after normalization) are dropped.
+    pub min_weight: f32,
+}
+
+/// Returns the range of `player` at the current node, i.e. the reach-weighted `weights` mapped
+/// back to combos.
+pub fn node_range(game: &PostFlopGame, player: usize, options: &NodeRangeOptions) -> Range {
+    let weights = game.weights(player);
+    let max_weight = weights.iter().fold(0.0f32, |acc, &w| acc.max(w));
+    let scale = match options.normalize && max_weight > 0.0 {
+        true => 1.0 / max_weight,
+        false => 1.0,
+    };
+
+    let mut range = Range::new();
+    for (&(card1, card2), &weight) in game.private_cards(player).iter().zip(weights) {
+        let weight = (weight * scale).min(1.0);
+        if weight >= options.min_weight && weight > 0.0 {
+            range.set_weight_by_cards(card1, card2, weight);
+        }
+    }
+    range
+}
+
+/// Saves the solved game to `path`, optionally truncated to `target_street` (0: flop, 1: turn,
+/// 2: river).
+pub fn save_game(
+    game: &mut PostFlopGame,
+    path: &str,
+    memo: &str,
+    compression_level: Option<i32>,
+    target_street: Option<u8>,
+) -> Result<(), Error> {
+    if !game.is_solved() {
+        return Err(Error::not_initialized("Game is not solved"));
+    }
+
+    let storage_mode = game.target_storage_mode();
+    if let Some(street) = target_street {
+        let mode = match street {
+            0 => BoardState::Flop,
+            1 => BoardState::Turn,
+            2 => BoardState::River,
+            _ => {
+                return Err(Error::invalid_field(
+                    "target_street",
+                    "Target street must be 0 (flop), 1 (turn) or 2 (river)",
+                ))
+            }
+        };
+        game.set_target_storage_mode(mode)?;
+    }
+
+    let result = save_data_to_file(&*game, memo, path, compression_level);
+    game.set_target_storage_mode(storage_mode).unwrap();
+    Ok(result?)
+}
+
+pub fn load_game(
+    path: &str,
+    max_memory_usage: Option<u64>,
+) -> Result<(PostFlopGame, String), Error> {
+    let (mut game, memo): (PostFlopGame, _) = load_data_from_file(path, max_memory_usage)?;
+    game.back_to_root();
+    Ok((game, memo))
+}
+
+/// Number of board cards up to which the strategies of `game` are stored; a game saved with a
+/// target street stores no strategy after it.
+#[inline]
+pub fn storage_board_len(game: &PostFlopGame) -> usize {
+    match game.storage_mode() {
+        BoardState::Flop => 3,
+        BoardState::Turn => 4,
+        BoardState::River => 5,
+    }
+}
+
+/// Plays `action` at the current node of `game` after checking that it is valid: the index of an
+/// available action at a decision node, or a possible card (or `usize::MAX` for any card) at a
+/// chance node whose next street is stored. `field` names the argument the action comes from in
+/// the error.
+pub fn play_checked(game: &mut PostFlopGame, action: usize, field: &str) -> Result<(), Error> {
+    if game.is_chance_node() && game.current_board().len() >= storage_board_len(game) {
+        let message = format!(
+            "Street after step {} is not stored in the loaded game",
+            game.history().len()
+        );
+        return Err(Error::invalid_field(field, message));
+    }
+
+    let valid = if game.is_terminal_node() {
+        false
+    } else if game.is_chance_node() {
+        let possible_cards = game.possible_cards();
+        match action {
+            usize::MAX => possible_cards != 0,
+            card => card < 52 && possible_cards & (1 << card) != 0,
+        }
+    } else {
+        action < game.available_actions().len()
+    };
+
+    if !valid {
+        let message = format!(
+            "Invalid action {} at step {}",
+            action as isize,
+            game.history().len()
+        );
+        return Err(Error::invalid_field(field, message));
+    }
+    game.play(action);
+    Ok(())
+}
+
+/// Moves to the node reached by playing `history` from the root, checking every step. The current
+/// node is kept if `history` is invalid.
+pub fn apply_history_checked(
+    game: &mut PostFlopGame,
+    history: &[usize],
+    field: &str,
+) -> Result<(), Error> {
+    let current = game.history().to_vec();
+    game.back_to_root();
+    for &action in history {
+        if let Err(e) = play_checked(game, action, field) {
+            game.apply_history(&current);
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// Runs `f` on the node reached by playing `append` from the current node, then restores the
+/// current node.
+pub fn with_appended<T>(
+    game: &mut PostFlopGame,
+    append: &[isize],
+    f: impl FnOnce(&PostFlopGame) -> T,
+) -> Result<T, Error> {
+    if append.is_empty() {
+        return Ok(f(game));
+    }
+    let history = game.history().to_vec();
+    for &action in append {
+        if let Err(e) = play_checked(game, action_usize(action), "append") {
+            game.apply_history(&history);
+            return Err(e);
+        }
+    }
+    let ret = f(game);
+    game.apply_history(&history);
+    Ok(ret)
+}
+
+pub fn actions(game: &PostFlopGame) -> Vec<String> {
+    if game.is_terminal_node() {
+        vec!["terminal".to_string()]
+    } else if game.is_chance_node() {
+        vec!["chance".to_string()]
+    } else {
+        game.available_actions()
+            .iter()
+            .map(|&x| action_to_string(x))
+            .collect()
+    }
+}
+
+pub fn current_player(game: &PostFlopGame) -> String {
+    if game.is_terminal_node() {
+        "terminal".to_string()
+    } else if game.is_chance_node() {
+        "chance".to_string()
+    } else if game.current_player() == 0 {
+        "oop".to_string()
+    } else {
+        "ip".to_string()
+    }
+}
+
+pub fn num_actions(game: &PostFlopGame) -> usize {
+    match game.is_chance_node() {
+        true => 0,
+        false => game.available_actions().len(),
+    }
+}
+
+#[derive(Serialize)]
+pub struct GameResultsResponse {
+    pub current_player: String,
+    pub num_actions: usize,
+    pub is_empty: i32,
+    pub eqr_base: [i32; 2],
+    pub weights: [Vec<f64>; 2],
+    pub normalizer: [Vec<f64>; 2],
+    pub equity: [Vec<f64>; 2],
+    pub ev: [Vec<f64>; 2],
+    pub eqr: [Vec<f64>; 2],
+    pub strategy: Vec<f64>,
+    pub action_ev: Vec<f64>,
+}
+
+pub fn get_results(game: &mut PostFlopGame) -> GameResultsResponse {
+    let total_bet_amount = game.total_bet_amount();
+    let pot_base = game.tree_config().starting_pot + total_bet_amount.iter().min().unwrap();
+    let eqr_base = [
+        pot_base + total_bet_amount[0],
+        pot_base + total_bet_amount[1],
+    ];
+
+    let trunc = |&w: &f32| if w < 0.0005 { 0.0 } else { round(w as f64) };
+    let weights = [
+        game.weights(0).iter().map(trunc).collect::<Vec<_>>(),
+        game.weights(1).iter().map(trunc).collect::<Vec<_>>(),
+    ];
+
+    let is_empty = |player: usize| weights[player].iter().all(|&w| w == 0.0);
+    let is_empty_flag = is_empty(0) as i32 + 2 * is_empty(1) as i32;
+
+    let mut normalizer = [Vec::new(), Vec::new()];
+    let mut equity = [Vec::new(), Vec::new()];
+    let mut ev = [Vec::new(), Vec::new()];
+    let mut eqr = [Vec::new(), Vec::new()];
+
+    if is_empty_flag > 0 {
+        normalizer[0].extend(weights[0].iter());
+        normalizer[1].extend(weights[1].iter());
+    } else {
+        game.cache_normalized_weights();
+
+        normalizer[0].extend(round_iter(game.normalized_weights(0).iter()));
+        normalizer[1].extend(round_iter(game.normalized_weights(1).iter()));
+
+        let equity_raw = [game.equity(0), game.equity(1)];
+        let ev_raw = [game.expected_values(0), game.expected_values(1)];
+
+        equity[0].extend(round_iter(equity_raw[0].iter()));
+        equity[1].extend(round_iter(equity_raw[1].iter()));
+        ev[0].extend(round_iter(ev_raw[0].iter()));
+        ev[1].extend(round_iter(ev_raw[1].iter()));
+
+        for player in 0..2 {
+            let pot = eqr_base[player] as f64;
+            for (&eq, &ev) in equity_raw[player].iter().zip(ev_raw[player].iter()) {
+                let (eq, ev) = (eq as f64, ev as f64);
+                if eq < 5e-7 {
+                    eqr[player].push(ev / 0.0);
+                } else {
+                    eqr[player].push(round(ev / (pot * eq)));
+                }
+            }
+        }
+    }
+
+    let mut strategy = Vec::new();
+    let mut action_ev = Vec::new();
+
+    if !game.is_terminal_node() && !game.is_chance_node() {
+        strategy.extend(round_iter(game.strategy().iter()));
+        if is_empty_flag == 0 {
+            action_ev.extend(round_iter(
+                game.expected_values_detail(game.current_player()).iter(),
+            ));
+        }
+    }
+
+    GameResultsResponse {
+        current_player: current_player(game),
+        num_actions: num_actions(game),
+        is_empty: is_empty_flag,
+        eqr_base,
+        weights,
+        normalizer,
+        equity,
+        ev,
+        eqr,
+        strategy,
+        action_ev,
+    }
+}
+
+#[derive(Serialize)]
+pub struct GameChanceReportsResponse {
+    pub status: Vec<i32>,
+    pub combos: [Vec<f64>; 2],
+    pub equity: [Vec<f64>; 2],
+    pub ev: [Vec<f64>; 2],
+    pub eqr: [Vec<f64>; 2],
+    pub strategy: Vec<f64>,
+}
+
+pub fn get_chance_reports(
+    game: &mut PostFlopGame,
+    append: &[isize],
+    num_actions: usize,
+) -> Result<GameChanceReportsResponse, Error> {
+    if !game.is_chance_node() || append.is_empty() {
+        return Err(Error::invalid_field("append", "Not a chance node"));
+    }
+
+    let history = game.history().to_vec();
+
+    let mut status = vec![0; 52]; // 0: not possible, 1: empty, 2: not empty
+    let mut combos = [vec![0.0; 52], vec![0.0; 52]];
+    let mut equity = [vec![0.0; 52], vec![0.0; 52]];
+    let mut ev = [vec![0.0; 52], vec![0.0; 52]];
+    let mut eqr = [vec![0.0; 52], vec![0.0; 52]];
+    let mut strategy = vec![0.0; num_actions * 52];
+
+    let possible_cards = game.possible_cards();
+    for chance in 0..52 {
+        if possible_cards & (1 << chance) == 0 {
+            continue;
+        }
+
+        game.play(chance);
+        for &action in &append[1..] {
+            if let Err(e) = play_checked(game, action_usize(action), "append") {
+                game.apply_history(&history);
+                return Err(e);
+            }
+        }
+        if !game.is_chance_node() && game.available_actions().len() < num_actions {
+            game.apply_history(&history);
+            let message = format!("Node after `append` has fewer than {num_actions} actions");
+            return Err(Error::invalid_field("num_actions", message));
+        }
+
+        let trunc = |&w: &f32| if w < 0.0005 { 0.0 } else { w };
+        let weights = [
+            game.weights(0).iter().map(trunc).collect::<Vec<_>>(),
+            game.weights(1).iter().map(trunc).collect::<Vec<_>>(),
+        ];
+
+        combos[0][chance] = round(weights[0].iter().fold(0.0, |acc, &w| acc + w as f64));
+        combos[1][chance] = round(weights[1].iter().fold(0.0, |acc, &w| acc + w as f64));
+
+        let is_empty = |player: usize| weights[player].iter().all(|&w| w == 0.0);
+        let is_empty_flag = [is_empty(0), is_empty(1)];
+
+        game.cache_normalized_weights();
+        let normalizer = [game.normalized_weights(0), game.normalized_weights(1)];
+
+        if !game.is_terminal_node() {
+            let current_player = game.current_player();
+            if !is_empty_flag[current_player] {
+                let strategy_tmp = game.strategy();
+                let num_hands = game.private_cards(current_player).len();
+                let ws = if is_empty_flag[current_player ^ 1] {
+                    &weights[current_player]
+                } else {
+                    normalizer[current_player]
+                };
+                for action in 0..num_actions {
+                    let slice = &strategy_tmp[action * num_hands..(action + 1) * num_hands];
+                    let strategy_summary = weighted_average(slice, ws);
+                    strategy[action * 52 + chance] = round(strategy_summary);
+                }
+            }
+        }
+
+        if is_empty_flag[0] || is_empty_flag[1] {
+            status[chance] = 1;
+            game.apply_history(&history);
+            continue;
+        }
+
+        status[chance] = 2;
+
+        let total_bet_amount = game.total_bet_amount();
+        let pot_base = game.tree_config().starting_pot + total_bet_amount.iter().min().unwrap();
+
+        for player in 0..2 {
+            let pot = (pot_base + total_bet_amount[player]) as f32;
+            let equity_tmp = weighted_average(&game.equity(player), normalizer[player]);
+            let ev_tmp = weighted_average(&game.expected_values(player), normalizer[player]);
+            equity[player][chance] = round(equity_tmp);
+            ev[player][chance] = round(ev_tmp);
+            eqr[player][chance] = round(ev_tmp / (pot as f64 * equity_tmp));
+        }
+
+        game.apply_history(&history);
+    }
+
+    Ok(GameChanceReportsResponse {
+        status,
+        combos,
+        equity,
+        ev,
+        eqr,
+        strategy,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncated_game_stops_at_stored_street() {
+        let ranges = [
+            Range::from_sanitized_str("AA,KK,QQ").unwrap(),
+            Range::from_sanitized_str("AK,AQ,JJ").unwrap(),
+        ];
+        let board = flop_from_str("Td9d6h").unwrap();
+        let config = TreeConfig {
+            initial_state: BoardState::Flop,
+            starting_pot: 100,
+            effective_stack: 100,
+            ..Default::default()
+        };
+        let card_config = card_config(&ranges, &board).unwrap();
+        let mut game =
+            PostFlopGame::with_config(card_config, ActionTree::new(config).unwrap()).unwrap();
+        game.allocate_memory(false);
+        solve(&mut game, 10, 0.0, false);
+
+        let path = std::env::temp_dir().join("desktop-postflop-truncated-test.bin");
+        let path = path.to_str().unwrap();
+        save_game(&mut game, path, "", None, Some(0)).unwrap();
+        let (mut loaded, _) = load_game(path, None).unwrap();
+        std::fs::remove_file(path).ok();
+        assert_eq!(storage_board_len(&loaded), 3);
+
+        // check-check reaches the turn, whose strategies were not saved
+        apply_history_checked(&mut loaded, &[0, 0], "history").unwrap();
+        assert!(loaded.is_chance_node());
+        let result = play_checked(&mut loaded, usize::MAX, "history");
+        assert!(matches!(result, Err(Error::InvalidField { .. })));
+        let result = apply_history_checked(&mut loaded, &[0, 0, usize::MAX, 0], "history");
+        assert!(matches!(result, Err(Error::InvalidField { .. })));
+        assert_eq!(loaded.history(), [0, 0]);
+    }
+}
@@ -0,0 +1,74 @@
+use crate::config::*;
+use crate::error::*;
+use crate::solver::*;
+use postflop_solver::*;
+
+/// Starting point of a game re-solved from a node of another game.
+pub struct Subgame {
+    pub ranges: [Range; 2],
+    pub board: Vec<u8>,
+    pub starting_pot: i32,
+    pub effective_stack: i32,
+}
+
+/// Returns whether the current node is the first decision node of a street.
+fn is_street_start(game: &mut PostFlopGame) -> bool {
+    let history = game.history().to_vec();
+    match history.split_last() {
+        None => true,
+        Some((_, parent)) => {
+            game.apply_history(parent);
+            let ret = game.is_chance_node();
+            game.apply_history(&history);
+            ret
+        }
+    }
+}
+
+/// Extracts the reach-weighted ranges, the pot, the remaining stack and the board at the current
+/// node of `game`, which must be solved and at the first decision node of a street.
+pub fn extract_subgame(game: &mut PostFlopGame) -> Result<Subgame, Error> {
+    ensure_solved(game)?;
+
+    if game.is_terminal_node() || game.is_chance_node() || !is_street_start(game) {
+        return Err(Error::invalid_field(
+            "history",
+            "Subgame must start at the first decision node of a street",
+        ));
+    }
+
+    let ranges = [0, 1].map(|player| node_range(game, player, &NodeRangeOptions::default()));
+
+    if ranges.iter().any(|range| range.is_empty()) {
+        return Err(Error::InvalidRange {
+            message: "Range is empty at the current node".to_string(),
+        });
+    }
+
+    // both players have put the same amount at the start of a street
+    let bet_amount = game.total_bet_amount()[0];
+    let config = game.tree_config();
+
+    Ok(Subgame {
+        ranges,
+        board: game.current_board(),
+        starting_pot: config.starting_pot + 2 * bet_amount,
+        effective_stack: config.effective_stack - bet_amount,
+    })
+}
+
+/// Initializes `game` for `subgame`. The pot and the stack of `params` are replaced by those of
+/// the subgame before validation; the other parameters apply to the remaining streets.
+pub fn init_subgame(
+    game: &mut PostFlopGame,
+    subgame: &Subgame,
+    params: &TreeConfigSpec,
+) -> Result<(), Error> {
+    let params = TreeConfigSpec {
+        starting_pot: subgame.starting_pot,
+        effective_stack: subgame.effective_stack,
+        ..params.clone()
+    };
+    check_spec(params.validate())?;
+    init_game(game, &subgame.ranges, &subgame.board, &params)
+}
@@ -0,0 +1,400 @@
+use crate::config::*;
+use crate::error::*;
+use crate::line::*;
+use crate::range::*;
+use postflop_solver::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+pub fn default_action_tree() -> ActionTree {
+    let tree_config = TreeConfig {
+        starting_pot: 1,
+        effective_stack: 1,
+        ..Default::default()
+    };
+    ActionTree::new(tree_config).unwrap()
+}
+
+/// Encodes `lines` as a comma-separated string.
+pub fn encode_lines(lines: &[Vec<Action>]) -> String {
+    lines
+        .iter()
+        .map(|l| encode_line(l))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Plays `action` and returns its index among the available actions, or -1 if it is not
+/// available.
+pub fn play_action(tree: &mut ActionTree, action: Action) -> i32 {
+    let available_actions = tree.available_actions();
+    if let Some(index) = available_actions.iter().position(|&a| a == action) {
+        tree.play(action).unwrap();
+        index as i32
+    } else {
+        -1
+    }
+}
+
+/// Output formats of `export_tree`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TreeExportFormat {
+    Dot,
+    Json,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TreeNodeKind {
+    Decision,
+    Chance,
+    Terminal,
+    /// Node of a removed line; it is not part of the tree.
+    Removed,
+}
+
+#[derive(Serialize)]
+pub struct TreeExportNode {
+    pub id: usize,
+    pub parent: Option<usize>,
+    /// Encoded line leading to the node (chance nodes are not part of lines).
+    pub line: String,
+    /// Action leading to the node, e.g. `"Bet:10"` (`None` for the root).
+    pub action: Option<String>,
+    pub kind: TreeNodeKind,
+    /// `"oop"` or `"ip"` at decision nodes.
+    pub player: Option<&'static str>,
+    /// Pot and remaining stacks; `None` for removed nodes.
+    pub pot: Option<i32>,
+    pub stacks: Option<[i32; 2]>,
+    /// Whether the node lies on or below an added line.
+    pub added: bool,
+    pub invalid_terminal: bool,
+}
+
+struct TreeWalker {
+    nodes: Vec<TreeExportNode>,
+    added_lines: Vec<Vec<Action>>,
+    removed_lines: Vec<Vec<Action>>,
+    invalid_terminals: Vec<Vec<Action>>,
+}
+
+impl TreeWalker {
+    fn push(
+        &mut self,
+        tree: &ActionTree,
+        parent: Option<usize>,
+        action: Option<String>,
+        kind: TreeNodeKind,
+        player: usize,
+    ) -> usize {
+        let history = tree.history();
+        let config = tree.config();
+        let bet = tree.total_bet_amount();
+        let id = self.nodes.len();
+        self.nodes.push(TreeExportNode {
+            id,
+            parent,
+            line: encode_line(history),
+            action,
+            kind,
+            player: (kind == TreeNodeKind::Decision).then_some(["oop", "ip"][player]),
+            pot: Some(config.starting_pot + bet[0] + bet[1]),
+            stacks: Some(bet.map(|b| config.effective_stack - b)),
+            added: self.added_lines.iter().any(|l| history.starts_with(l)),
+            invalid_terminal: self.invalid_terminals.iter().any(|l| l == history),
+        });
+        id
+    }
+
+    fn walk(&mut self, tree: &mut ActionTree, parent: Option<usize>, action: Option<String>) {
+        if tree.is_terminal_node() {
+            self.push(tree, parent, action, TreeNodeKind::Terminal, 0);
+            return;
+        }
+
+        // the chance node shares its line with the first decision node of the next street
+        let (parent, action, player) = match tree.is_chance_node() {
+            true => {
+                let id = self.push(tree, parent, action, TreeNodeKind::Chance, 0);
+                (Some(id), Some("Chance".to_string()), 0)
+            }
+            false => (parent, action, next_player(tree.history())),
+        };
+        let id = self.push(tree, parent, action, TreeNodeKind::Decision, player);
+
+        let history = tree.history().to_vec();
+        for &action in tree.available_actions().to_vec().iter() {
+            tree.play(action).unwrap();
+            self.walk(tree, Some(id), Some(action_to_string(action)));
+            tree.apply_history(&history).unwrap();
+        }
+
+        let removed = self
+            .removed_lines
+            .iter()
+            .filter_map(|l| l.split_last())
+            .filter(|(_, prefix)| *prefix == history.as_slice())
+            .map(|(&action, _)| action)
+            .collect::<Vec<_>>();
+        for action in removed {
+            let mut line = history.clone();
+            line.push(action);
+            self.nodes.push(TreeExportNode {
+                id: self.nodes.len(),
+                parent: Some(id),
+                line: encode_line(&line),
+                action: Some(action_to_string(action)),
+                kind: TreeNodeKind::Removed,
+                player: None,
+                pot: None,
+                stacks: None,
+                added: false,
+                invalid_terminal: false,
+            });
+        }
+    }
+}
+
+/// Returns the player to act after `history`, which must end on a decision node.
+fn next_player(history: &[Action]) -> usize {
+    // OOP acts first on each street; a street ends with a check-check or a call
+    let mut player = 0;
+    let mut prev = Action::None;
+    for &action in history {
+        let street_end =
+            action == Action::Call || (action == Action::Check && prev == Action::Check);
+        player = if street_end { 0 } else { player ^ 1 };
+        prev = if street_end { Action::None } else { action };
+    }
+    player
+}
+
+/// Walks every node of `tree` from the root in depth-first order. Nodes of removed lines are
+/// included with the kind `removed`. The current node of `tree` is restored afterwards.
+pub fn tree_nodes(tree: &mut ActionTree) -> Vec<TreeExportNode> {
+    let history = tree.history().to_vec();
+    let mut walker = TreeWalker {
+        nodes: Vec::new(),
+        added_lines: tree.added_lines().to_vec(),
+        removed_lines: tree.removed_lines().to_vec(),
+        invalid_terminals: tree.invalid_terminals(),
+    };
+
+    tree.back_to_root();
+    walker.walk(tree, None, None);
+    tree.apply_history(&history).unwrap();
+    walker.nodes
+}
+
+fn dot_string(nodes: &[TreeExportNode]) -> String {
+    let mut ret = String::from("digraph ActionTree {\n  node [shape=box, fontname=monospace];\n");
+
+    for node in nodes {
+        let title = match node.kind {
+            TreeNodeKind::Decision => node.player.unwrap().to_uppercase(),
+            kind => format!("{kind:?}"),
+        };
+        let mut label = title;
+        if let (Some(pot), Some(stacks)) = (node.pot, node.stacks) {
+            label += &format!("\\npot {pot}\\nstacks {} / {}", stacks[0], stacks[1]);
+        }
+
+        let style = match (node.kind, node.invalid_terminal, node.added) {
+            (TreeNodeKind::Removed, _, _) => ", style=dashed, color=gray, fontcolor=gray",
+            (_, true, _) => ", color=red, fontcolor=red",
+            (_, _, true) => ", color=green4",
+            _ if node.kind == TreeNodeKind::Chance => ", shape=ellipse",
+            _ => "",
+        };
+        ret += &format!("  n{} [label=\"{label}\"{style}];\n", node.id);
+
+        if let Some(parent) = node.parent {
+            let action = node.action.as_deref().unwrap_or_default();
+            let style = match node.kind {
+                TreeNodeKind::Removed => ", style=dashed, color=gray",
+                _ => "",
+            };
+            ret += &format!("  n{parent} -> n{} [label=\"{action}\"{style}];\n", node.id);
+        }
+    }
+
+    ret += "}\n";
+    ret
+}
+
+/// Exports every node of `tree` in `format`.
+pub fn export_tree(tree: &mut ActionTree, format: TreeExportFormat) -> Result<String, Error> {
+    let nodes = tree_nodes(tree);
+    match format {
+        TreeExportFormat::Dot => Ok(dot_string(&nodes)),
+        TreeExportFormat::Json => Ok(serde_json::to_string(&nodes).map_err(|e| e.to_string())?),
+    }
+}
+
+/// Node counts and bet sizes of one street.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct StreetStats {
+    pub decision_nodes: usize,
+    /// Chance nodes dealing the card of this street.
+    pub chance_nodes: usize,
+    pub terminal_nodes: usize,
+    /// Maximum number of raises in a single line of this street.
+    pub max_raises: usize,
+    /// Number of distinct bet and raise amounts (including all-ins).
+    pub bet_sizes: usize,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct TreeStats {
+    /// Flop, turn and river; streets before the initial state are all zeros.
+    pub streets: [StreetStats; 3],
+    pub max_raises: usize,
+}
+
+struct StatsWalker {
+    stats: TreeStats,
+    bet_sizes: [BTreeSet<i32>; 3],
+}
+
+impl StatsWalker {
+    fn walk(&mut self, tree: &mut ActionTree, street: usize, raises: Option<usize>) {
+        if tree.is_terminal_node() {
+            self.stats.streets[street].terminal_nodes += 1;
+            return;
+        }
+
+        let (street, raises) = match tree.is_chance_node() {
+            true => {
+                self.stats.streets[street + 1].chance_nodes += 1;
+                (street + 1, None)
+            }
+            false => (street, raises),
+        };
+        self.stats.streets[street].decision_nodes += 1;
+
+        let history = tree.history().to_vec();
+        for &action in tree.available_actions().to_vec().iter() {
+            // `raises` is `None` until the first bet of the street
+            let raises = match action {
+                Action::Bet(amount) | Action::Raise(amount) | Action::AllIn(amount) => {
+                    self.bet_sizes[street].insert(amount);
+                    Some(raises.map_or(0, |r| r + 1))
+                }
+                _ => raises,
+            };
+            let street_stats = &mut self.stats.streets[street];
+            street_stats.max_raises = street_stats.max_raises.max(raises.unwrap_or(0));
+
+            tree.play(action).unwrap();
+            self.walk(tree, street, raises);
+            tree.apply_history(&history).unwrap();
+        }
+    }
+}
+
+/// Counts the nodes of `tree` per street. The current node of `tree` is restored afterwards.
+pub fn collect_tree_stats(tree: &mut ActionTree) -> TreeStats {
+    let history = tree.history().to_vec();
+    let mut walker = StatsWalker {
+        stats: TreeStats::default(),
+        bet_sizes: Default::default(),
+    };
+
+    tree.back_to_root();
+    walker.walk(tree, tree.config().initial_state as usize, None);
+    tree.apply_history(&history).unwrap();
+
+    let mut stats = walker.stats;
+    for (street, sizes) in stats.streets.iter_mut().zip(&walker.bet_sizes) {
+        street.bet_sizes = sizes.len();
+    }
+    stats.max_raises = stats.streets.iter().map(|s| s.max_raises).max().unwrap();
+    stats
+}
+
+/// Returns a copy of `tree` built anew from its configuration and its added and removed lines.
+/// `ActionTree` does not implement `Clone`, so replaying the edits is the only way to copy it.
+pub fn rebuild_action_tree(tree: &ActionTree) -> Result<ActionTree, Error> {
+    let mut ret = ActionTree::new(tree.config().clone())?;
+    for line in tree.added_lines() {
+        ret.add_line(line)?;
+    }
+    for line in tree.removed_lines() {
+        ret.remove_line(line)?;
+    }
+    Ok(ret)
+}
+
+/// Returns the card configuration of a game built from `tree`, `ranges` and `board`.
+pub fn tree_card_config(
+    tree: &ActionTree,
+    ranges: &[Range],
+    board: &[Card],
+) -> Result<CardConfig, Error> {
+    if board_state(board.len())? != tree.config().initial_state {
+        return Err(Error::invalid_board("Board length does not match the tree"));
+    }
+    card_mask(board)?;
+    card_config(ranges, board)
+}
+
+/// Returns the approximate memory usage (uncompressed, compressed) in bytes of a game built
+/// from `tree` (e.g. a copy made with `rebuild_action_tree`), `ranges` and `board`, without
+/// allocating it.
+pub fn estimate_memory(
+    tree: ActionTree,
+    ranges: &[Range],
+    board: &[Card],
+) -> Result<(u64, u64), Error> {
+    let card_config = tree_card_config(&tree, ranges, board)?;
+    let game = PostFlopGame::with_config(card_config, tree)?;
+    Ok(game.memory_usage())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebuilt_tree_matches_edited_tree() {
+        let sizes = BetSizeOptions::try_from(("50%", "2x")).unwrap();
+        let config = TreeConfig {
+            initial_state: BoardState::River,
+            starting_pot: 100,
+            effective_stack: 300,
+            river_bet_sizes: [sizes.clone(), sizes],
+            ..Default::default()
+        };
+        let mut tree = ActionTree::new(config).unwrap();
+
+        // remove under add: drop the default raise below an added bet
+        tree.add_line(&[Action::Bet(20)]).unwrap();
+        tree.apply_history(&[Action::Bet(20)]).unwrap();
+        let actions = tree.available_actions().to_vec();
+        let raise = actions
+            .iter()
+            .find(|a| matches!(a, Action::Raise(_)))
+            .unwrap();
+        tree.remove_action(*raise).unwrap();
+        tree.back_to_root();
+
+        // add under remove: restore a removed bet and add a raise below it
+        let bet = [Action::Check, Action::Bet(50)];
+        tree.remove_line(&bet).unwrap();
+        tree.remove_line(&[Action::Bet(50)]).unwrap();
+        tree.add_line(&bet).unwrap();
+        tree.add_line(&[Action::Check, Action::Bet(50), Action::Raise(200)])
+            .unwrap();
+
+        assert!(!tree.added_lines().is_empty());
+        assert!(!tree.removed_lines().is_empty());
+
+        let mut rebuilt = rebuild_action_tree(&tree).unwrap();
+        assert_eq!(rebuilt.added_lines(), tree.added_lines());
+        assert_eq!(rebuilt.removed_lines(), tree.removed_lines());
+        let nodes = |tree: &mut ActionTree| serde_json::to_value(tree_nodes(tree)).unwrap();
+        assert_eq!(nodes(&mut rebuilt), nodes(&mut tree));
+    }
+}
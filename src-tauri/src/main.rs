@@ -5,29 +5,37 @@
 
 mod bunching;
 mod range;
+mod session;
 mod solver;
 mod tree;
 use crate::bunching::*;
 use crate::range::*;
+use crate::session::*;
 use crate::solver::*;
 use crate::tree::*;
 
-use postflop_solver::*;
+use desktop_postflop::error::Error;
+use desktop_postflop::session::SessionManager;
 use rayon::{ThreadPool, ThreadPoolBuilder};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use sysinfo::{System, SystemExt};
 
 fn main() {
     tauri::Builder::default()
-        .manage(Mutex::new(RangeManager::default()))
-        .manage(Mutex::new(default_action_tree()))
-        .manage(Mutex::new(None as Option<BunchingData>))
-        .manage(Mutex::new(PostFlopGame::default()))
-        .manage(Mutex::new(ThreadPoolBuilder::new().build().unwrap()))
+        .manage(Mutex::new(SessionManager::default()))
+        .manage(Mutex::new(Arc::new(
+            ThreadPoolBuilder::new().build().unwrap(),
+        )))
         .invoke_handler(tauri::generate_handler![
             os_name,
             memory,
             set_num_threads,
+            session_create,
+            session_list,
+            session_close,
+            session_current,
+            session_switch,
+            session_memory_usage,
             range_num_combos,
             range_clear,
             range_invert,
@@ -36,7 +44,26 @@ fn main() {
             range_to_string,
             range_get_weights,
             range_raw_data,
+            range_get_combo,
+            range_set_combo,
+            range_set_raw_data,
+            range_suit_uniform,
+            range_num_combos_live,
+            range_get_weights_live,
+            range_live_combos,
+            range_combine,
+            range_scale,
+            range_clamp,
+            range_import,
+            range_export,
+            range_undo,
+            range_redo,
+            range_history_state,
+            range_diff,
+            range_top_percent,
+            range_equity,
             tree_new,
+            tree_validate_spec,
             tree_added_lines,
             tree_removed_lines,
             tree_invalid_terminals,
@@ -51,10 +78,16 @@ fn main() {
             tree_remove_current_node,
             tree_delete_added_line,
             tree_delete_removed_line,
+            tree_export,
+            tree_stats,
+            tree_estimate_memory,
+            tree_edit_matching,
             bunching_init,
             bunching_clear,
             bunching_progress,
             game_init,
+            game_validate_spec,
+            game_init_from_tree,
             game_private_cards,
             game_memory_usage,
             game_memory_usage_bunching,
@@ -63,11 +96,27 @@ fn main() {
             game_solve_step,
             game_exploitability,
             game_finalize,
+            game_solve_start,
+            game_solve_pause,
+            game_solve_resume,
+            game_solve_cancel,
+            game_solve_progress,
+            game_lock_strategy,
+            game_lock_hands,
+            game_unlock_strategy,
+            game_locked_nodes,
+            game_save,
+            game_load,
             game_apply_history,
             game_total_bet_amount,
             game_actions_after,
             game_possible_cards,
             game_get_results,
+            game_hand_categories,
+            game_export_node,
+            game_node_range,
+            game_resolve_subgame,
+            game_dump_tree,
             game_get_chance_reports
         ])
         .run(tauri::generate_context!())
@@ -100,9 +149,15 @@ fn memory() -> (u64, u64) {
 }
 
 #[tauri::command]
-fn set_num_threads(pool_state: tauri::State<Mutex<ThreadPool>>, num_threads: usize) {
-    *pool_state.lock().unwrap() = ThreadPoolBuilder::new()
+fn set_num_threads(
+    pool_state: tauri::State<Mutex<Arc<ThreadPool>>>,
+    num_threads: usize,
+) -> Result<(), Error> {
+    let pool = ThreadPoolBuilder::new()
         .num_threads(num_threads)
         .build()
-        .unwrap();
+        .map_err(|e| Error::from(e.to_string()))?;
+    // a running solve keeps the previous pool until it stops
+    *pool_state.lock().unwrap() = Arc::new(pool);
+    Ok(())
 }
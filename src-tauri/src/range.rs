@@ -1,93 +1,373 @@
+use desktop_postflop::equity::*;
+use desktop_postflop::error::*;
+use desktop_postflop::format::*;
+use desktop_postflop::range::*;
+use desktop_postflop::session::*;
 use postflop_solver::*;
-use std::cmp::Ordering;
-use std::sync::Mutex;
-
-#[derive(Default)]
-pub struct RangeManager(pub [Range; 6]);
+use rayon::ThreadPool;
+use std::sync::{Arc, Mutex};
 
 #[tauri::command]
-pub fn range_num_combos(range_state: tauri::State<Mutex<RangeManager>>, player: usize) -> f64 {
-    let range = &(range_state.lock().unwrap().0)[player];
-    range.raw_data().iter().fold(0.0, |acc, &x| acc + x as f64)
+pub fn range_num_combos(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    player: usize,
+) -> Result<f64, Error> {
+    let session = get_session(&sessions, session_id)?;
+    let range = &session.ranges.lock().unwrap().get(player)?;
+    Ok(num_combos(range))
 }
 
 #[tauri::command]
-pub fn range_clear(range_state: tauri::State<Mutex<RangeManager>>, player: usize) {
-    let range = &mut (range_state.lock().unwrap().0)[player];
-    range.clear();
+pub fn range_clear(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    player: usize,
+) -> Result<(), Error> {
+    let session = get_session(&sessions, session_id)?;
+    let mut ranges = session.ranges.lock().unwrap();
+    ranges.modify(player, |range| range.clear())
 }
 
 #[tauri::command]
-pub fn range_invert(range_state: tauri::State<Mutex<RangeManager>>, player: usize) {
-    let range = &mut (range_state.lock().unwrap().0)[player];
-    range.invert();
+pub fn range_invert(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    player: usize,
+) -> Result<(), Error> {
+    let session = get_session(&sessions, session_id)?;
+    let mut ranges = session.ranges.lock().unwrap();
+    ranges.modify(player, |range| range.invert())
 }
 
 #[tauri::command]
 pub fn range_update(
-    range_state: tauri::State<Mutex<RangeManager>>,
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
     player: usize,
     row: u8,
     col: u8,
     weight: f32,
-) {
-    let range = &mut (range_state.lock().unwrap().0)[player];
-    let rank1 = 13 - row;
-    let rank2 = 13 - col;
-    match row.cmp(&col) {
-        Ordering::Equal => range.set_weight_pair(rank1, weight),
-        Ordering::Less => range.set_weight_suited(rank1, rank2, weight),
-        Ordering::Greater => range.set_weight_offsuit(rank1, rank2, weight),
-    }
+) -> Result<(), Error> {
+    check_cell(row, col, weight)?;
+    let session = get_session(&sessions, session_id)?;
+    let mut ranges = session.ranges.lock().unwrap();
+    ranges.modify(player, |range| update_weight(range, row, col, weight))
 }
 
 #[tauri::command]
 pub fn range_from_string(
-    range_state: tauri::State<Mutex<RangeManager>>,
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
     player: usize,
     str: String,
-) -> Option<String> {
-    let range = &mut (range_state.lock().unwrap().0)[player];
-    let result = Range::from_sanitized_str(str.as_str());
-    if let Ok(unwrap) = result {
-        *range = unwrap;
-        None
-    } else {
-        result.err()
-    }
+) -> Result<(), Error> {
+    let session = get_session(&sessions, session_id)?;
+    let range = Range::from_sanitized_str(str.as_str())
+        .map_err(|message| Error::InvalidRange { message })?;
+    let mut ranges = session.ranges.lock().unwrap();
+    ranges.set(player, range)
 }
 
 #[tauri::command]
-pub fn range_to_string(range_state: tauri::State<Mutex<RangeManager>>, player: usize) -> String {
-    let range = &(range_state.lock().unwrap().0)[player];
-    range.to_string()
+pub fn range_to_string(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    player: usize,
+) -> Result<String, Error> {
+    let session = get_session(&sessions, session_id)?;
+    let range = &session.ranges.lock().unwrap().get(player)?;
+    Ok(range.to_string())
 }
 
 #[tauri::command]
 pub fn range_get_weights(
-    range_state: tauri::State<Mutex<RangeManager>>,
-    player: usize,
-) -> Vec<f32> {
-    let range = &(range_state.lock().unwrap().0)[player];
-    let mut weights = vec![0.0; 13 * 13];
-
-    for row in 0..13 {
-        for col in 0..13 {
-            let rank1 = 12 - row as u8;
-            let rank2 = 12 - col as u8;
-            weights[row * 13 + col] = match row.cmp(&col) {
-                Ordering::Equal => range.get_weight_pair(rank1),
-                Ordering::Less => range.get_weight_suited(rank1, rank2),
-                Ordering::Greater => range.get_weight_offsuit(rank1, rank2),
-            };
-        }
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    player: usize,
+) -> Result<Vec<f32>, Error> {
+    let session = get_session(&sessions, session_id)?;
+    let range = &session.ranges.lock().unwrap().get(player)?;
+    Ok(class_weights(range))
+}
+
+#[tauri::command]
+pub fn range_raw_data(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    player: usize,
+) -> Result<Vec<f32>, Error> {
+    let session = get_session(&sessions, session_id)?;
+    let range = &session.ranges.lock().unwrap().get(player)?;
+    Ok(range.raw_data().to_vec())
+}
+
+/// Computes the equities of the ranges in the slots `players` against each other.
+#[tauri::command(async)]
+pub fn range_equity(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    pool_state: tauri::State<Mutex<Arc<ThreadPool>>>,
+    session_id: u32,
+    players: [usize; 2],
+    board: Vec<u8>,
+    dead_cards: Vec<u8>,
+) -> Result<EquityResult, Error> {
+    let session = get_session(&sessions, session_id)?;
+    let ranges = {
+        let ranges = session.ranges.lock().unwrap();
+        [ranges.get(players[0])?, ranges.get(players[1])?]
+    };
+    let pool = pool_state.lock().unwrap();
+    pool.install(|| compute_equity([&ranges[0], &ranges[1]], &board, &dead_cards))
+}
+
+#[tauri::command]
+pub fn range_get_combo(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    player: usize,
+    card1: u8,
+    card2: u8,
+) -> Result<f32, Error> {
+    check_combo(card1, card2, 0.0)?;
+    let session = get_session(&sessions, session_id)?;
+    let range = &session.ranges.lock().unwrap().get(player)?;
+    Ok(range.get_weight_by_cards(card1, card2))
+}
+
+#[tauri::command]
+pub fn range_set_combo(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    player: usize,
+    card1: u8,
+    card2: u8,
+    weight: f32,
+) -> Result<(), Error> {
+    check_combo(card1, card2, weight)?;
+    let session = get_session(&sessions, session_id)?;
+    let mut ranges = session.ranges.lock().unwrap();
+    ranges.modify(player, |range| {
+        range.set_weight_by_cards(card1, card2, weight)
+    })
+}
+
+/// Replaces the range with `weights` indexed by `card_pair_to_index` (1326 entries), i.e. the
+/// layout of `range_raw_data`.
+#[tauri::command]
+pub fn range_set_raw_data(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    player: usize,
+    weights: Vec<f32>,
+) -> Result<(), Error> {
+    let session = get_session(&sessions, session_id)?;
+    let range =
+        Range::from_raw_data(&weights).map_err(|message| Error::InvalidRange { message })?;
+    let mut ranges = session.ranges.lock().unwrap();
+    ranges.set(player, range)
+}
+
+#[tauri::command]
+pub fn range_suit_uniform(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    player: usize,
+) -> Result<Vec<bool>, Error> {
+    let session = get_session(&sessions, session_id)?;
+    let range = &session.ranges.lock().unwrap().get(player)?;
+    Ok(suit_uniform_flags(range))
+}
+
+/// Same as `range_num_combos`, but ignores the combos blocked by `board` and `dead_cards`.
+#[tauri::command]
+pub fn range_num_combos_live(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    player: usize,
+    board: Vec<u8>,
+    dead_cards: Vec<u8>,
+) -> Result<f64, Error> {
+    let dead_mask = card_mask(&[board, dead_cards].concat())?;
+    let session = get_session(&sessions, session_id)?;
+    let range = &session.ranges.lock().unwrap().get(player)?;
+    Ok(live_num_combos(range, dead_mask))
+}
+
+/// Same as `range_get_weights`, but averages over the combos not blocked by `board` and
+/// `dead_cards`.
+#[tauri::command]
+pub fn range_get_weights_live(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    player: usize,
+    board: Vec<u8>,
+    dead_cards: Vec<u8>,
+) -> Result<Vec<f32>, Error> {
+    let dead_mask = card_mask(&[board, dead_cards].concat())?;
+    let session = get_session(&sessions, session_id)?;
+    let range = &session.ranges.lock().unwrap().get(player)?;
+    Ok(live_class_weights(range, dead_mask))
+}
+
+#[tauri::command]
+pub fn range_live_combos(board: Vec<u8>, dead_cards: Vec<u8>) -> Result<Vec<u8>, Error> {
+    let dead_mask = card_mask(&[board, dead_cards].concat())?;
+    Ok(live_class_combos(dead_mask))
+}
+
+/// Stores the result of `op` applied to the ranges `lhs` and `rhs` in the slot `target`.
+#[tauri::command]
+pub fn range_combine(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    lhs: usize,
+    rhs: RangeOperand,
+    op: RangeOp,
+    target: usize,
+) -> Result<(), Error> {
+    check_slot(target)?;
+    let session = get_session(&sessions, session_id)?;
+    let mut ranges = session.ranges.lock().unwrap();
+    let (lhs, rhs) = (ranges.get(lhs)?, rhs.resolve(&ranges)?);
+    ranges.set(target, combine_ranges(&lhs, &rhs, op)?)
+}
+
+#[tauri::command]
+pub fn range_scale(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    player: usize,
+    factor: f32,
+    target: usize,
+) -> Result<(), Error> {
+    check_slot(target)?;
+    let session = get_session(&sessions, session_id)?;
+    let mut ranges = session.ranges.lock().unwrap();
+    let range = scale_range(&ranges.get(player)?, factor)?;
+    ranges.set(target, range)
+}
+
+#[tauri::command]
+pub fn range_clamp(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    player: usize,
+    min: f32,
+    max: f32,
+    target: usize,
+) -> Result<(), Error> {
+    if !(0.0 <= min && min <= max && max <= 1.0) {
+        return Err(Error::InvalidRange {
+            message: "Bounds must satisfy 0 <= min <= max <= 1".to_string(),
+        });
     }
+    check_slot(target)?;
+    let session = get_session(&sessions, session_id)?;
+    let mut ranges = session.ranges.lock().unwrap();
+    let range = clamp_range(&ranges.get(player)?, min, max)?;
+    ranges.set(target, range)
+}
+
+#[tauri::command]
+pub fn range_import(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    player: usize,
+    text: String,
+    format: RangeFormat,
+) -> Result<RangeImportResult, Error> {
+    let (range, result) = import_range(&text, format)?;
+    let session = get_session(&sessions, session_id)?;
+    session.ranges.lock().unwrap().set(player, range)?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub fn range_export(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    player: usize,
+    format: RangeFormat,
+) -> Result<String, Error> {
+    let session = get_session(&sessions, session_id)?;
+    let range = &session.ranges.lock().unwrap().get(player)?;
+    Ok(export_range(range, format))
+}
 
-    weights
+/// Returns whether undo and redo are available after the operation.
+#[tauri::command]
+pub fn range_undo(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    player: usize,
+) -> Result<(bool, bool), Error> {
+    let session = get_session(&sessions, session_id)?;
+    let mut ranges = session.ranges.lock().unwrap();
+    ranges.undo(player)?;
+    ranges.history_state(player)
 }
 
+/// Returns whether undo and redo are available after the operation.
 #[tauri::command]
-pub fn range_raw_data(range_state: tauri::State<Mutex<RangeManager>>, player: usize) -> Vec<f32> {
-    let range = &(range_state.lock().unwrap().0)[player];
-    range.raw_data().to_vec()
+pub fn range_redo(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    player: usize,
+) -> Result<(bool, bool), Error> {
+    let session = get_session(&sessions, session_id)?;
+    let mut ranges = session.ranges.lock().unwrap();
+    ranges.redo(player)?;
+    ranges.history_state(player)
+}
+
+#[tauri::command]
+pub fn range_history_state(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    player: usize,
+) -> Result<(bool, bool), Error> {
+    let session = get_session(&sessions, session_id)?;
+    let ranges = session.ranges.lock().unwrap();
+    ranges.history_state(player)
+}
+
+#[tauri::command]
+pub fn range_diff(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    lhs: usize,
+    rhs: usize,
+) -> Result<RangeDiff, Error> {
+    let session = get_session(&sessions, session_id)?;
+    let ranges = session.ranges.lock().unwrap();
+    Ok(compare_ranges(&ranges.get(lhs)?, &ranges.get(rhs)?))
+}
+
+/// Stores in the slot `target` the combos of the range `player` ranked between the given
+/// percentiles.
+#[tauri::command(async)]
+pub fn range_top_percent(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    pool_state: tauri::State<Mutex<Arc<ThreadPool>>>,
+    session_id: u32,
+    player: usize,
+    options: TopPercentOptions,
+    target: usize,
+) -> Result<(), Error> {
+    check_slot(target)?;
+    let session = get_session(&sessions, session_id)?;
+    let (range, opponent) = {
+        let ranges = session.ranges.lock().unwrap();
+        let opponent = match options.by {
+            RankBy::Equity { opponent } => Some(ranges.get(opponent)?),
+            RankBy::Strength => None,
+        };
+        (ranges.get(player)?, opponent)
+    };
+
+    let pool = pool_state.lock().unwrap();
+    let result = pool.install(|| top_percent_range(&range, opponent.as_ref(), &options))?;
+    let mut ranges = session.ranges.lock().unwrap();
+    ranges.set(target, result)
 }
@@ -0,0 +1,41 @@
+use desktop_postflop::error::*;
+use desktop_postflop::session::*;
+use std::sync::Mutex;
+
+#[tauri::command]
+pub fn session_create(sessions: tauri::State<Mutex<SessionManager>>, name: Option<String>) -> u32 {
+    sessions.lock().unwrap().create(name)
+}
+
+#[tauri::command]
+pub fn session_list(sessions: tauri::State<Mutex<SessionManager>>) -> Vec<SessionInfo> {
+    sessions.lock().unwrap().list()
+}
+
+#[tauri::command]
+pub fn session_close(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+) -> Result<u32, Error> {
+    let mut sessions = sessions.lock().unwrap();
+    sessions.close(session_id)?;
+    Ok(sessions.current())
+}
+
+#[tauri::command]
+pub fn session_current(sessions: tauri::State<Mutex<SessionManager>>) -> u32 {
+    sessions.lock().unwrap().current()
+}
+
+#[tauri::command]
+pub fn session_switch(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+) -> Result<(), Error> {
+    sessions.lock().unwrap().switch(session_id)
+}
+
+#[tauri::command]
+pub fn session_memory_usage(sessions: tauri::State<Mutex<SessionManager>>) -> u64 {
+    sessions.lock().unwrap().memory_usage()
+}
@@ -1,534 +1,546 @@
-use crate::range::*;
+use desktop_postflop::config::*;
+use desktop_postflop::dump::*;
+use desktop_postflop::error::*;
+use desktop_postflop::export::*;
+use desktop_postflop::hand::*;
+use desktop_postflop::job::*;
+use desktop_postflop::locking::*;
+use desktop_postflop::range::*;
+use desktop_postflop::session::*;
+use desktop_postflop::solver::*;
+use desktop_postflop::subgame::*;
 use postflop_solver::*;
 use rayon::ThreadPool;
 use serde::Serialize;
-use std::sync::Mutex;
-
-#[inline]
-fn decode_action(action: &str) -> Action {
-    match action {
-        "F" => Action::Fold,
-        "X" => Action::Check,
-        "C" => Action::Call,
-        _ => {
-            let mut chars = action.chars();
-            let first_char = chars.next().unwrap();
-            let amount = chars.as_str().parse().unwrap();
-            match first_char {
-                'B' => Action::Bet(amount),
-                'R' => Action::Raise(amount),
-                'A' => Action::AllIn(amount),
-                _ => unreachable!(),
-            }
-        }
-    }
+use std::sync::{Arc, Mutex};
+use tauri::Manager;
+
+#[tauri::command(async)]
+pub fn game_init(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    spec: GameSpec,
+) -> Result<(), Error> {
+    check_spec(spec.validate())?;
+
+    let session = get_session(&sessions, session_id)?;
+    let _job = lock_idle(&session.job)?;
+    let ranges = &session.ranges.lock().unwrap().0;
+    let mut game = session.game.lock().unwrap();
+    let result = init_game(&mut game, ranges, &spec.board, &spec.tree);
+    session.locks.lock().unwrap().clear();
+    session.update_memory_usage(&game);
+    result
 }
 
-#[inline]
-fn action_usize(action: isize) -> usize {
-    match action {
-        -1 => usize::MAX,
-        a => a as usize,
-    }
+/// Initializes the game with the action tree edited in the tree editor of the session.
+#[tauri::command(async)]
+pub fn game_init_from_tree(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    board: Vec<u8>,
+) -> Result<(), Error> {
+    let session = get_session(&sessions, session_id)?;
+    let _job = lock_idle(&session.job)?;
+    let ranges = &session.ranges.lock().unwrap().0;
+    let tree = session.tree.lock().unwrap();
+    let mut game = session.game.lock().unwrap();
+    let result = init_game_from_tree(&mut game, ranges, &board, &tree);
+    session.locks.lock().unwrap().clear();
+    session.update_memory_usage(&game);
+    result
 }
 
-#[inline]
-fn round(value: f64) -> f64 {
-    if value < 1.0 {
-        (value * 1000000.0).round() / 1000000.0
-    } else if value < 10.0 {
-        (value * 100000.0).round() / 100000.0
-    } else if value < 100.0 {
-        (value * 10000.0).round() / 10000.0
-    } else if value < 1000.0 {
-        (value * 1000.0).round() / 1000.0
-    } else if value < 10000.0 {
-        (value * 100.0).round() / 100.0
-    } else {
-        (value * 10.0).round() / 10.0
-    }
+#[tauri::command]
+pub fn game_validate_spec(spec: GameSpec) -> Vec<Error> {
+    spec.validate()
 }
 
-#[inline]
-fn round_iter<'a>(iter: impl Iterator<Item = &'a f32> + 'a) -> impl Iterator<Item = f64> + 'a {
-    iter.map(|&x| round(x as f64))
+#[tauri::command]
+pub fn game_private_cards(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+) -> Result<[Vec<u16>; 2], Error> {
+    let session = get_session(&sessions, session_id)?;
+    let game = session.game.lock().unwrap();
+    Ok(private_cards(&game))
 }
 
-#[inline]
-pub fn weighted_average(slice: &[f32], weights: &[f32]) -> f64 {
-    let mut sum = 0.0;
-    let mut weight_sum = 0.0;
-    for (&value, &weight) in slice.iter().zip(weights.iter()) {
-        sum += value as f64 * weight as f64;
-        weight_sum += weight as f64;
+/// Builds the range of `player` at the current node. The range is stored in the slot `target`
+/// if given, and returned as a string.
+#[tauri::command]
+pub fn game_node_range(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    player: usize,
+    options: NodeRangeOptions,
+    target: Option<usize>,
+) -> Result<String, Error> {
+    if player > 1 {
+        return Err(Error::invalid_field(
+            "player",
+            "Player must be 0 (OOP) or 1 (IP)",
+        ));
+    }
+    if let Some(target) = target {
+        check_slot(target)?;
+    }
+
+    let session = get_session(&sessions, session_id)?;
+    let range = {
+        // release the game before locking the ranges (ranges are locked first elsewhere)
+        let game = session.game.lock().unwrap();
+        ensure_allocated(&game)?;
+        node_range(&game, player, &options)
+    };
+    if let Some(target) = target {
+        session.ranges.lock().unwrap().set(target, range)?;
     }
-    sum / weight_sum
+    Ok(range.to_string())
 }
 
+/// Re-solves the subgame starting at the current node with the tree parameters `params`, either
+/// in a new session or in place of the current game. Returns the ID of the session that holds the
+/// new game.
+///
+/// The current node must be the first decision node of a street (right after a chance node or
+/// the root); other nodes are rejected, since the ranges alone do not describe a subgame that
+/// starts in the middle of a street.
 #[tauri::command(async)]
-pub fn game_init(
-    range_state: tauri::State<Mutex<RangeManager>>,
-    game_state: tauri::State<Mutex<PostFlopGame>>,
-    board: Vec<u8>,
-    starting_pot: i32,
-    effective_stack: i32,
-    rake_rate: f64,
-    rake_cap: f64,
-    donk_option: bool,
-    oop_flop_bet: String,
-    oop_flop_raise: String,
-    oop_turn_bet: String,
-    oop_turn_raise: String,
-    oop_turn_donk: String,
-    oop_river_bet: String,
-    oop_river_raise: String,
-    oop_river_donk: String,
-    ip_flop_bet: String,
-    ip_flop_raise: String,
-    ip_turn_bet: String,
-    ip_turn_raise: String,
-    ip_river_bet: String,
-    ip_river_raise: String,
-    add_allin_threshold: f64,
-    force_allin_threshold: f64,
-    merging_threshold: f64,
-    added_lines: String,
-    removed_lines: String,
-) -> Option<String> {
-    let (turn, river, state) = match board.len() {
-        3 => (NOT_DEALT, NOT_DEALT, BoardState::Flop),
-        4 => (board[3], NOT_DEALT, BoardState::Turn),
-        5 => (board[3], board[4], BoardState::River),
-        _ => return Some("Invalid board length".to_string()),
+pub fn game_resolve_subgame(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    params: TreeConfigSpec,
+    new_session: bool,
+    name: Option<String>,
+) -> Result<u32, Error> {
+    let session = get_session(&sessions, session_id)?;
+    let job = lock_idle(&session.job)?;
+    let subgame = extract_subgame(&mut session.game.lock().unwrap())?;
+
+    // when re-solving in place, keep the job guard so that no solve starts until the new game is
+    // ready; otherwise release it before locking the session manager, which `close` locks first
+    let (_job, new_target) = match new_session {
+        false => (Some(job), None),
+        true => {
+            drop(job);
+            let id = sessions.lock().unwrap().create(name);
+            (None, Some((id, get_session(&sessions, id)?)))
+        }
     };
-
-    let ranges = &range_state.lock().unwrap().0;
-    let card_config = CardConfig {
-        range: ranges[..2].try_into().unwrap(),
-        flop: board[..3].try_into().unwrap(),
-        turn,
-        river,
+    let (target_id, target) = match &new_target {
+        None => (session_id, &session),
+        Some((id, target)) => (*id, target),
     };
 
-    let tree_config = TreeConfig {
-        initial_state: state,
-        starting_pot,
-        effective_stack,
-        rake_rate,
-        rake_cap,
-        flop_bet_sizes: [
-            BetSizeOptions::try_from((oop_flop_bet.as_str(), oop_flop_raise.as_str())).unwrap(),
-            BetSizeOptions::try_from((ip_flop_bet.as_str(), ip_flop_raise.as_str())).unwrap(),
-        ],
-        turn_bet_sizes: [
-            BetSizeOptions::try_from((oop_turn_bet.as_str(), oop_turn_raise.as_str())).unwrap(),
-            BetSizeOptions::try_from((ip_turn_bet.as_str(), ip_turn_raise.as_str())).unwrap(),
-        ],
-        river_bet_sizes: [
-            BetSizeOptions::try_from((oop_river_bet.as_str(), oop_river_raise.as_str())).unwrap(),
-            BetSizeOptions::try_from((ip_river_bet.as_str(), ip_river_raise.as_str())).unwrap(),
-        ],
-        turn_donk_sizes: match donk_option {
-            false => None,
-            true => DonkSizeOptions::try_from(oop_turn_donk.as_str()).ok(),
-        },
-        river_donk_sizes: match donk_option {
-            false => None,
-            true => DonkSizeOptions::try_from(oop_river_donk.as_str()).ok(),
-        },
-        add_allin_threshold,
-        force_allin_threshold,
-        merging_threshold,
-    };
+    let mut game = target.game.lock().unwrap();
+    let result = init_subgame(&mut game, &subgame, &params);
+    target.locks.lock().unwrap().clear();
+    target.update_memory_usage(&game);
+    drop(game);
 
-    let mut action_tree = ActionTree::new(tree_config).unwrap();
-
-    if !added_lines.is_empty() {
-        for added_line in added_lines.split(',') {
-            let line = added_line
-                .split(&['-', '|'][..])
-                .map(decode_action)
-                .collect::<Vec<_>>();
-            if action_tree.add_line(&line).is_err() {
-                return Some("Failed to add line (loaded broken tree?)".to_string());
-            }
+    if let Err(e) = result {
+        if new_session {
+            sessions.lock().unwrap().close(target_id)?;
         }
+        return Err(e);
     }
 
-    if !removed_lines.is_empty() {
-        for removed_line in removed_lines.split(',') {
-            let line = removed_line
-                .split(&['-', '|'][..])
-                .map(decode_action)
-                .collect::<Vec<_>>();
-            if action_tree.remove_line(&line).is_err() {
-                return Some("Failed to remove line (loaded broken tree?)".to_string());
-            }
-        }
-    }
+    let mut ranges = target.ranges.lock().unwrap();
+    ranges.set(0, subgame.ranges[0])?;
+    ranges.set(1, subgame.ranges[1])?;
 
-    let mut game = game_state.lock().unwrap();
-    game.update_config(card_config, action_tree).err()
+    Ok(target_id)
 }
 
 #[tauri::command]
-pub fn game_private_cards(game_state: tauri::State<Mutex<PostFlopGame>>) -> [Vec<u16>; 2] {
-    let game = game_state.lock().unwrap();
-    let convert = |player: usize| {
-        game.private_cards(player)
-            .iter()
-            .map(|&(c1, c2)| (c1 as u16) | (c2 as u16) << 8)
-            .collect()
-    };
-    [convert(0), convert(1)]
+pub fn game_memory_usage(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+) -> Result<(u64, u64), Error> {
+    let session = get_session(&sessions, session_id)?;
+    let game = session.game.lock().unwrap();
+    Ok(game.memory_usage())
 }
 
 #[tauri::command]
-pub fn game_memory_usage(game_state: tauri::State<Mutex<PostFlopGame>>) -> (u64, u64) {
-    let game = game_state.lock().unwrap();
-    game.memory_usage()
-}
-
-#[tauri::command]
-pub fn game_memory_usage_bunching(game_state: tauri::State<Mutex<PostFlopGame>>) -> u64 {
-    let game = game_state.lock().unwrap();
-    game.memory_usage_bunching()
+pub fn game_memory_usage_bunching(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+) -> Result<u64, Error> {
+    let session = get_session(&sessions, session_id)?;
+    let game = session.game.lock().unwrap();
+    Ok(game.memory_usage_bunching())
 }
 
 #[tauri::command(async)]
 pub fn game_allocate_memory(
-    game_state: tauri::State<Mutex<PostFlopGame>>,
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
     enable_compression: bool,
-) {
-    let mut game = game_state.lock().unwrap();
+) -> Result<(), Error> {
+    let session = get_session(&sessions, session_id)?;
+    let _job = lock_idle(&session.job)?;
+    let mut game = session.game.lock().unwrap();
     game.allocate_memory(enable_compression);
+    session.update_memory_usage(&game);
+    let locks = session.locks.lock().unwrap();
+    apply_locks(&mut game, &locks)
 }
 
 #[tauri::command(async)]
 pub fn game_set_bunching(
-    bunching_state: tauri::State<Mutex<Option<BunchingData>>>,
-    game_state: tauri::State<Mutex<PostFlopGame>>,
-) -> Option<String> {
-    let bunching_data = bunching_state.lock().unwrap();
-    let bunching_data = bunching_data.as_ref().unwrap();
-    let mut game = game_state.lock().unwrap();
-    game.set_bunching_effect(bunching_data).err()
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+) -> Result<(), Error> {
+    let session = get_session(&sessions, session_id)?;
+    let _job = lock_idle(&session.job)?;
+    let bunching_data = session.bunching.lock().unwrap();
+    let bunching_data = bunching_data
+        .as_ref()
+        .ok_or_else(|| Error::not_initialized("Bunching data is not initialized"))?;
+    let mut game = session.game.lock().unwrap();
+    Ok(game.set_bunching_effect(bunching_data)?)
 }
 
 #[tauri::command(async)]
 pub fn game_solve_step(
-    game_state: tauri::State<Mutex<PostFlopGame>>,
-    pool_state: tauri::State<Mutex<ThreadPool>>,
+    sessions: tauri::State<Mutex<SessionManager>>,
+    pool_state: tauri::State<Mutex<Arc<ThreadPool>>>,
+    session_id: u32,
     current_iteration: u32,
-) {
-    let game = game_state.lock().unwrap();
+) -> Result<(), Error> {
+    let session = get_session(&sessions, session_id)?;
+    let _job = lock_idle(&session.job)?;
+    let game = session.game.lock().unwrap();
+    ensure_solvable(&game)?;
     let pool = pool_state.lock().unwrap();
     pool.install(|| solve_step(&*game, current_iteration));
+    Ok(())
 }
 
 #[tauri::command(async)]
 pub fn game_exploitability(
-    game_state: tauri::State<Mutex<PostFlopGame>>,
-    pool_state: tauri::State<Mutex<ThreadPool>>,
-) -> f32 {
-    let game = game_state.lock().unwrap();
+    sessions: tauri::State<Mutex<SessionManager>>,
+    pool_state: tauri::State<Mutex<Arc<ThreadPool>>>,
+    session_id: u32,
+) -> Result<f32, Error> {
+    let session = get_session(&sessions, session_id)?;
+    let game = session.game.lock().unwrap();
+    ensure_allocated(&game)?;
     let pool = pool_state.lock().unwrap();
-    pool.install(|| compute_exploitability(&*game))
+    Ok(pool.install(|| compute_exploitability(&*game)))
 }
 
 #[tauri::command(async)]
 pub fn game_finalize(
-    game_state: tauri::State<Mutex<PostFlopGame>>,
-    pool_state: tauri::State<Mutex<ThreadPool>>,
-) {
+    sessions: tauri::State<Mutex<SessionManager>>,
+    pool_state: tauri::State<Mutex<Arc<ThreadPool>>>,
+    session_id: u32,
+) -> Result<(), Error> {
+    let session = get_session(&sessions, session_id)?;
+    let _job = lock_idle(&session.job)?;
+    let mut game = session.game.lock().unwrap();
+    ensure_solvable(&game)?;
+    let game = &mut *game;
     let pool = pool_state.lock().unwrap();
-    pool.install(|| finalize(&mut *game_state.lock().unwrap()));
+    pool.install(|| finalize(game));
+    Ok(())
 }
 
-#[tauri::command]
-pub fn game_apply_history(game_state: tauri::State<Mutex<PostFlopGame>>, history: Vec<usize>) {
-    let mut game = game_state.lock().unwrap();
-    game.apply_history(&history);
+#[derive(Clone, Serialize)]
+struct SolveProgressEvent {
+    session_id: u32,
+    #[serde(flatten)]
+    progress: SolveProgress,
 }
 
-#[tauri::command]
-pub fn game_total_bet_amount(
-    game_state: tauri::State<Mutex<PostFlopGame>>,
-    append: Vec<isize>,
-) -> [i32; 2] {
-    let mut game = game_state.lock().unwrap();
-    if append.is_empty() {
-        return game.total_bet_amount();
-    }
-    let history = game.history().to_vec();
-    for &action in &append {
-        game.play(action_usize(action));
-    }
-    let ret = game.total_bet_amount();
-    game.apply_history(&history);
-    ret
-}
-
-fn actions(game: &PostFlopGame) -> Vec<String> {
-    if game.is_terminal_node() {
-        vec!["terminal".to_string()]
-    } else if game.is_chance_node() {
-        vec!["chance".to_string()]
-    } else {
-        game.available_actions()
-            .iter()
-            .map(|&x| match x {
-                Action::Fold => "Fold:0".to_string(),
-                Action::Check => "Check:0".to_string(),
-                Action::Call => "Call:0".to_string(),
-                Action::Bet(amount) => format!("Bet:{amount}"),
-                Action::Raise(amount) => format!("Raise:{amount}"),
-                Action::AllIn(amount) => format!("Allin:{amount}"),
-                _ => unreachable!(),
+fn spawn_solve(app: tauri::AppHandle, session_id: u32, session: Arc<Session>) {
+    std::thread::spawn(move || {
+        // release the pool lock so that other sessions can solve and the number of threads can
+        // be changed while this solve is running
+        let pool = Arc::clone(&app.state::<Mutex<Arc<ThreadPool>>>().lock().unwrap());
+
+        // on failure the job is reset to `Idle`, which the last progress event reports
+        pool.install(|| {
+            run_solve(&session.game, &session.job, |progress| {
+                let event = SolveProgressEvent {
+                    session_id,
+                    progress: *progress,
+                };
+                app.emit_all("solve-progress", event).ok();
             })
-            .collect()
-    }
+        })
+        .ok();
+    });
 }
 
 #[tauri::command]
-pub fn game_actions_after(
-    game_state: tauri::State<Mutex<PostFlopGame>>,
-    append: Vec<isize>,
-) -> Vec<String> {
-    let mut game = game_state.lock().unwrap();
-    if append.is_empty() {
-        return actions(&game);
+pub fn game_solve_start(
+    app: tauri::AppHandle,
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    options: SolveOptions,
+) -> Result<(), Error> {
+    let session = get_session(&sessions, session_id)?;
+    let mut job = session.job.lock().unwrap();
+    if job.is_running() {
+        return Err(Error::solver_state("Solver is already running"));
     }
-    let history = game.history().to_vec();
-    for &action in &append {
-        game.play(action_usize(action));
+    ensure_solvable(&session.game.lock().unwrap())?;
+    job.start(options);
+    drop(job);
+    spawn_solve(app, session_id, session);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn game_solve_pause(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+) -> Result<(), Error> {
+    let session = get_session(&sessions, session_id)?;
+    let mut job = session.job.lock().unwrap();
+    if job.is_running() {
+        job.request = Some(SolveStatus::Paused);
     }
-    let ret = actions(&game);
-    game.apply_history(&history);
-    ret
+    Ok(())
 }
 
 #[tauri::command]
-pub fn game_possible_cards(game_state: tauri::State<Mutex<PostFlopGame>>) -> u64 {
-    let game = game_state.lock().unwrap();
-    game.possible_cards()
-}
-
-fn current_player(game: &PostFlopGame) -> String {
-    if game.is_terminal_node() {
-        "terminal".to_string()
-    } else if game.is_chance_node() {
-        "chance".to_string()
-    } else if game.current_player() == 0 {
-        "oop".to_string()
-    } else {
-        "ip".to_string()
+pub fn game_solve_resume(
+    app: tauri::AppHandle,
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+) -> Result<(), Error> {
+    let session = get_session(&sessions, session_id)?;
+    let mut job = session.job.lock().unwrap();
+    if job.progress.status != SolveStatus::Paused {
+        return Err(Error::solver_state("Solver is not paused"));
     }
+    job.progress.status = SolveStatus::Running;
+    job.request = None;
+    drop(job);
+    spawn_solve(app, session_id, session);
+    Ok(())
 }
 
-pub fn num_actions(game: &PostFlopGame) -> usize {
-    match game.is_chance_node() {
-        true => 0,
-        false => game.available_actions().len(),
+#[tauri::command]
+pub fn game_solve_cancel(
+    app: tauri::AppHandle,
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+) -> Result<(), Error> {
+    let session = get_session(&sessions, session_id)?;
+    let mut job = session.job.lock().unwrap();
+    match job.progress.status {
+        SolveStatus::Running => job.request = Some(SolveStatus::Cancelled),
+        SolveStatus::Paused => {
+            // finalize the paused game on the solving thread
+            job.progress.status = SolveStatus::Running;
+            job.request = Some(SolveStatus::Cancelled);
+            drop(job);
+            spawn_solve(app, session_id, session);
+        }
+        _ => {}
     }
+    Ok(())
 }
 
-#[derive(Serialize)]
-pub struct GameResultsResponse {
-    current_player: String,
-    num_actions: usize,
-    is_empty: i32,
-    eqr_base: [i32; 2],
-    weights: [Vec<f64>; 2],
-    normalizer: [Vec<f64>; 2],
-    equity: [Vec<f64>; 2],
-    ev: [Vec<f64>; 2],
-    eqr: [Vec<f64>; 2],
-    strategy: Vec<f64>,
-    action_ev: Vec<f64>,
+#[tauri::command]
+pub fn game_solve_progress(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+) -> Result<SolveProgress, Error> {
+    let session = get_session(&sessions, session_id)?;
+    let progress = session.job.lock().unwrap().progress;
+    Ok(progress)
 }
 
 #[tauri::command]
-pub fn game_get_results(game_state: tauri::State<Mutex<PostFlopGame>>) -> GameResultsResponse {
-    let mut game = game_state.lock().unwrap();
-
-    let total_bet_amount = game.total_bet_amount();
-    let pot_base = game.tree_config().starting_pot + total_bet_amount.iter().min().unwrap();
-    let eqr_base = [
-        pot_base + total_bet_amount[0],
-        pot_base + total_bet_amount[1],
-    ];
-
-    let trunc = |&w: &f32| if w < 0.0005 { 0.0 } else { round(w as f64) };
-    let weights = [
-        game.weights(0).iter().map(trunc).collect::<Vec<_>>(),
-        game.weights(1).iter().map(trunc).collect::<Vec<_>>(),
-    ];
-
-    let is_empty = |player: usize| weights[player].iter().all(|&w| w == 0.0);
-    let is_empty_flag = is_empty(0) as i32 + 2 * is_empty(1) as i32;
-
-    let mut normalizer = [Vec::new(), Vec::new()];
-    let mut equity = [Vec::new(), Vec::new()];
-    let mut ev = [Vec::new(), Vec::new()];
-    let mut eqr = [Vec::new(), Vec::new()];
-
-    if is_empty_flag > 0 {
-        normalizer[0].extend(weights[0].iter());
-        normalizer[1].extend(weights[1].iter());
-    } else {
-        game.cache_normalized_weights();
-
-        normalizer[0].extend(round_iter(game.normalized_weights(0).iter()));
-        normalizer[1].extend(round_iter(game.normalized_weights(1).iter()));
-
-        let equity_raw = [game.equity(0), game.equity(1)];
-        let ev_raw = [game.expected_values(0), game.expected_values(1)];
-
-        equity[0].extend(round_iter(equity_raw[0].iter()));
-        equity[1].extend(round_iter(equity_raw[1].iter()));
-        ev[0].extend(round_iter(ev_raw[0].iter()));
-        ev[1].extend(round_iter(ev_raw[1].iter()));
-
-        for player in 0..2 {
-            let pot = eqr_base[player] as f64;
-            for (&eq, &ev) in equity_raw[player].iter().zip(ev_raw[player].iter()) {
-                let (eq, ev) = (eq as f64, ev as f64);
-                if eq < 5e-7 {
-                    eqr[player].push(ev / 0.0);
-                } else {
-                    eqr[player].push(round(ev / (pot * eq)));
-                }
-            }
-        }
-    }
+pub fn game_lock_strategy(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    history: Vec<usize>,
+    strategy: Vec<f32>,
+) -> Result<(), Error> {
+    let session = get_session(&sessions, session_id)?;
+    let _job = lock_idle(&session.job)?;
+    let mut game = session.game.lock().unwrap();
+    let mut locks = session.locks.lock().unwrap();
+    lock_node(&mut game, &mut locks, &history, strategy)
+}
 
-    let mut strategy = Vec::new();
-    let mut action_ev = Vec::new();
+#[tauri::command]
+pub fn game_lock_hands(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    history: Vec<usize>,
+    hands: Vec<usize>,
+    frequencies: Vec<f32>,
+) -> Result<(), Error> {
+    let session = get_session(&sessions, session_id)?;
+    let _job = lock_idle(&session.job)?;
+    let mut game = session.game.lock().unwrap();
+    let mut locks = session.locks.lock().unwrap();
+    lock_hands(&mut game, &mut locks, &history, &hands, &frequencies)
+}
 
-    if !game.is_terminal_node() && !game.is_chance_node() {
-        strategy.extend(round_iter(game.strategy().iter()));
-        if is_empty_flag == 0 {
-            action_ev.extend(round_iter(
-                game.expected_values_detail(game.current_player()).iter(),
-            ));
-        }
-    }
+#[tauri::command]
+pub fn game_unlock_strategy(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    history: Vec<usize>,
+) -> Result<(), Error> {
+    let session = get_session(&sessions, session_id)?;
+    let _job = lock_idle(&session.job)?;
+    let mut game = session.game.lock().unwrap();
+    let mut locks = session.locks.lock().unwrap();
+    unlock_node(&mut game, &mut locks, &history)
+}
 
-    GameResultsResponse {
-        current_player: current_player(&game),
-        num_actions: num_actions(&game),
-        is_empty: is_empty_flag,
-        eqr_base,
-        weights,
-        normalizer,
-        equity,
-        ev,
-        eqr,
-        strategy,
-        action_ev,
-    }
+#[tauri::command]
+pub fn game_locked_nodes(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+) -> Result<Vec<NodeLock>, Error> {
+    let session = get_session(&sessions, session_id)?;
+    let locks = session.locks.lock().unwrap().clone();
+    Ok(locks)
+}
+
+#[tauri::command(async)]
+pub fn game_save(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    path: String,
+    memo: String,
+    compression_level: Option<i32>,
+    target_street: Option<u8>,
+) -> Result<(), Error> {
+    let session = get_session(&sessions, session_id)?;
+    let mut game = session.game.lock().unwrap();
+    save_game(&mut game, &path, &memo, compression_level, target_street)
 }
 
-#[derive(Serialize)]
-pub struct GameChanceReportsResponse {
-    status: Vec<i32>,
-    combos: [Vec<f64>; 2],
-    equity: [Vec<f64>; 2],
-    ev: [Vec<f64>; 2],
-    eqr: [Vec<f64>; 2],
-    strategy: Vec<f64>,
+#[tauri::command(async)]
+pub fn game_load(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    path: String,
+    max_memory_usage: Option<u64>,
+) -> Result<String, Error> {
+    let session = get_session(&sessions, session_id)?;
+    let _job = lock_idle(&session.job)?;
+    let (loaded, memo) = load_game(&path, max_memory_usage)?;
+    let mut game = session.game.lock().unwrap();
+    *game = loaded;
+    session.locks.lock().unwrap().clear();
+    session.update_memory_usage(&game);
+    Ok(memo)
 }
 
 #[tauri::command]
-pub fn game_get_chance_reports(
-    game_state: tauri::State<Mutex<PostFlopGame>>,
-    append: Vec<isize>,
-    num_actions: usize,
-) -> GameChanceReportsResponse {
-    let mut game = game_state.lock().unwrap();
-    let history = game.history().to_vec();
-
-    let mut status = vec![0; 52]; // 0: not possible, 1: empty, 2: not empty
-    let mut combos = [vec![0.0; 52], vec![0.0; 52]];
-    let mut equity = [vec![0.0; 52], vec![0.0; 52]];
-    let mut ev = [vec![0.0; 52], vec![0.0; 52]];
-    let mut eqr = [vec![0.0; 52], vec![0.0; 52]];
-    let mut strategy = vec![0.0; num_actions * 52];
-
-    let possible_cards = game.possible_cards();
-    for chance in 0..52 {
-        if possible_cards & (1 << chance) == 0 {
-            continue;
-        }
+pub fn game_apply_history(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    history: Vec<usize>,
+) -> Result<(), Error> {
+    let session = get_session(&sessions, session_id)?;
+    let mut game = session.game.lock().unwrap();
+    ensure_allocated(&game)?;
+    apply_history_checked(&mut game, &history, "history")
+}
 
-        game.play(chance);
-        for &action in &append[1..] {
-            game.play(action_usize(action));
-        }
+#[tauri::command]
+pub fn game_total_bet_amount(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    append: Vec<isize>,
+) -> Result<[i32; 2], Error> {
+    let session = get_session(&sessions, session_id)?;
+    let mut game = session.game.lock().unwrap();
+    ensure_allocated(&game)?;
+    with_appended(&mut game, &append, |game| game.total_bet_amount())
+}
 
-        let trunc = |&w: &f32| if w < 0.0005 { 0.0 } else { w };
-        let weights = [
-            game.weights(0).iter().map(trunc).collect::<Vec<_>>(),
-            game.weights(1).iter().map(trunc).collect::<Vec<_>>(),
-        ];
-
-        combos[0][chance] = round(weights[0].iter().fold(0.0, |acc, &w| acc + w as f64));
-        combos[1][chance] = round(weights[1].iter().fold(0.0, |acc, &w| acc + w as f64));
-
-        let is_empty = |player: usize| weights[player].iter().all(|&w| w == 0.0);
-        let is_empty_flag = [is_empty(0), is_empty(1)];
-
-        game.cache_normalized_weights();
-        let normalizer = [game.normalized_weights(0), game.normalized_weights(1)];
-
-        if !game.is_terminal_node() {
-            let current_player = game.current_player();
-            if !is_empty_flag[current_player] {
-                let strategy_tmp = game.strategy();
-                let num_hands = game.private_cards(current_player).len();
-                let ws = if is_empty_flag[current_player ^ 1] {
-                    &weights[current_player]
-                } else {
-                    normalizer[current_player]
-                };
-                for action in 0..num_actions {
-                    let slice = &strategy_tmp[action * num_hands..(action + 1) * num_hands];
-                    let strategy_summary = weighted_average(slice, ws);
-                    strategy[action * 52 + chance] = round(strategy_summary);
-                }
-            }
-        }
+#[tauri::command]
+pub fn game_actions_after(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    append: Vec<isize>,
+) -> Result<Vec<String>, Error> {
+    let session = get_session(&sessions, session_id)?;
+    let mut game = session.game.lock().unwrap();
+    ensure_allocated(&game)?;
+    with_appended(&mut game, &append, actions)
+}
 
-        if is_empty_flag[0] || is_empty_flag[1] {
-            status[chance] = 1;
-            game.apply_history(&history);
-            continue;
-        }
+#[tauri::command]
+pub fn game_possible_cards(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+) -> Result<u64, Error> {
+    let session = get_session(&sessions, session_id)?;
+    let game = session.game.lock().unwrap();
+    ensure_allocated(&game)?;
+    Ok(game.possible_cards())
+}
 
-        status[chance] = 2;
+#[tauri::command]
+pub fn game_get_results(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+) -> Result<GameResultsResponse, Error> {
+    let session = get_session(&sessions, session_id)?;
+    let mut game = session.game.lock().unwrap();
+    ensure_solved(&game)?;
+    Ok(get_results(&mut game))
+}
 
-        let total_bet_amount = game.total_bet_amount();
-        let pot_base = game.tree_config().starting_pot + total_bet_amount.iter().min().unwrap();
+#[tauri::command]
+pub fn game_hand_categories(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+) -> Result<HandCategoryReport, Error> {
+    let session = get_session(&sessions, session_id)?;
+    let mut game = session.game.lock().unwrap();
+    hand_category_report(&mut game)
+}
 
-        for player in 0..2 {
-            let pot = (pot_base + total_bet_amount[player]) as f32;
-            let equity_tmp = weighted_average(&game.equity(player), normalizer[player]);
-            let ev_tmp = weighted_average(&game.expected_values(player), normalizer[player]);
-            equity[player][chance] = round(equity_tmp);
-            ev[player][chance] = round(ev_tmp);
-            eqr[player][chance] = round(ev_tmp / (pot as f64 * equity_tmp));
-        }
+#[tauri::command(async)]
+pub fn game_export_node(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    path: String,
+    format: ExportFormat,
+) -> Result<(), Error> {
+    let session = get_session(&sessions, session_id)?;
+    let mut game = session.game.lock().unwrap();
+    export_node(&mut game, &path, format)
+}
 
-        game.apply_history(&history);
-    }
+#[tauri::command(async)]
+pub fn game_dump_tree(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    path: String,
+    chance_cards: Option<Vec<u8>>,
+) -> Result<usize, Error> {
+    let session = get_session(&sessions, session_id)?;
+    let mut game = session.game.lock().unwrap();
+    dump_tree_to_file(&mut game, &path, &DumpOptions { chance_cards })
+}
 
-    GameChanceReportsResponse {
-        status,
-        combos,
-        equity,
-        ev,
-        eqr,
-        strategy,
-    }
+#[tauri::command]
+pub fn game_get_chance_reports(
+    sessions: tauri::State<Mutex<SessionManager>>,
+    session_id: u32,
+    append: Vec<isize>,
+    num_actions: usize,
+) -> Result<GameChanceReportsResponse, Error> {
+    let session = get_session(&sessions, session_id)?;
+    let mut game = session.game.lock().unwrap();
+    ensure_solved(&game)?;
+    get_chance_reports(&mut game, &append, num_actions)
 }
@@ -1,6 +1,51 @@
 import { invoke } from "@tauri-apps/api";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
 import { Results, ChanceReports } from "./result-types";
 
+export type BackendError = {
+  kind:
+    | "invalid_board"
+    | "invalid_bet_size"
+    | "invalid_line"
+    | "invalid_field"
+    | "invalid_range"
+    | "engine"
+    | "not_initialized"
+    | "solver_state"
+    | "invalid_session";
+  message: string;
+  field?: string;
+  position?: number;
+  line?: string;
+};
+
+export const errorToString = (error: unknown): string => {
+  if (typeof error === "string") return error;
+  const e = error as BackendError;
+  switch (e.kind) {
+    case "invalid_bet_size":
+      return `${e.message} (${e.field}, position ${e.position})`;
+    case "invalid_line":
+      return `${e.message} (${e.line})`;
+    case "invalid_field":
+      return `${e.message} (${e.field})`;
+    default:
+      return e.message;
+  }
+};
+
+const invokeError = async (
+  cmd: string,
+  args?: Record<string, unknown>
+): Promise<string | null> => {
+  try {
+    await invoke(cmd, args);
+    return null;
+  } catch (error) {
+    return errorToString(error);
+  }
+};
+
 export const osName = async (): Promise<"windows" | "macos" | "linux"> => {
   return await invoke("os_name");
 };
@@ -13,18 +58,54 @@ export const setNumThreads = async (numThreads: number) => {
   await invoke("set_num_threads", { numThreads });
 };
 
+/* Sessions */
+
+let sessionId = 0;
+
+export type SessionInfo = {
+  id: number;
+  name: string;
+  is_current: boolean;
+  memory_usage: number;
+};
+
+export const currentSessionId = (): number => {
+  return sessionId;
+};
+
+export const sessionCreate = async (name: string | null): Promise<number> => {
+  return await invoke("session_create", { name });
+};
+
+export const sessionList = async (): Promise<SessionInfo[]> => {
+  return await invoke("session_list");
+};
+
+export const sessionClose = async (id: number) => {
+  sessionId = await invoke("session_close", { sessionId: id });
+};
+
+export const sessionSwitch = async (id: number) => {
+  await invoke("session_switch", { sessionId: id });
+  sessionId = id;
+};
+
+export const sessionMemoryUsage = async (): Promise<number> => {
+  return await invoke("session_memory_usage");
+};
+
 /* Ranges */
 
 export const rangeNumCombos = async (player: number): Promise<number> => {
-  return await invoke("range_num_combos", { player });
+  return await invoke("range_num_combos", { sessionId, player });
 };
 
 export const rangeClear = async (player: number) => {
-  await invoke("range_clear", { player });
+  await invoke("range_clear", { sessionId, player });
 };
 
 export const rangeInvert = async (player: number) => {
-  await invoke("range_invert", { player });
+  await invoke("range_invert", { sessionId, player });
 };
 
 export const rangeUpdate = async (
@@ -33,258 +114,634 @@ export const rangeUpdate = async (
   col: number,
   weight: number
 ) => {
-  await invoke("range_update", { player, row, col, weight });
+  await invoke("range_update", { sessionId, player, row, col, weight });
 };
 
 export const rangeFromString = async (
   player: number,
   str: string
 ): Promise<string | null> => {
-  return await invoke("range_from_string", { player, str });
+  return await invokeError("range_from_string", { sessionId, player, str });
 };
 
 export const rangeToString = async (player: number): Promise<string> => {
-  return await invoke("range_to_string", { player });
+  return await invoke("range_to_string", { sessionId, player });
 };
 
 export const rangeGetWeights = async (player: number): Promise<number[]> => {
-  return await invoke("range_get_weights", { player });
+  return await invoke("range_get_weights", { sessionId, player });
 };
 
 export const rangeRawData = async (player: number): Promise<number[]> => {
-  return await invoke("range_raw_data", { player });
+  return await invoke("range_raw_data", { sessionId, player });
+};
+
+export const rangeGetCombo = async (
+  player: number,
+  card1: number,
+  card2: number
+): Promise<number> => {
+  return await invoke("range_get_combo", { sessionId, player, card1, card2 });
+};
+
+export const rangeSetCombo = async (
+  player: number,
+  card1: number,
+  card2: number,
+  weight: number
+): Promise<string | null> => {
+  return await invokeError("range_set_combo", {
+    sessionId,
+    player,
+    card1,
+    card2,
+    weight,
+  });
+};
+
+export const rangeSetRawData = async (
+  player: number,
+  weights: number[]
+): Promise<string | null> => {
+  return await invokeError("range_set_raw_data", {
+    sessionId,
+    player,
+    weights,
+  });
+};
+
+export const rangeSuitUniform = async (player: number): Promise<boolean[]> => {
+  return await invoke("range_suit_uniform", { sessionId, player });
+};
+
+export const rangeNumCombosLive = async (
+  player: number,
+  board: number[],
+  deadCards: number[]
+): Promise<number> => {
+  return await invoke("range_num_combos_live", {
+    sessionId,
+    player,
+    board,
+    deadCards,
+  });
+};
+
+export const rangeGetWeightsLive = async (
+  player: number,
+  board: number[],
+  deadCards: number[]
+): Promise<number[]> => {
+  return await invoke("range_get_weights_live", {
+    sessionId,
+    player,
+    board,
+    deadCards,
+  });
+};
+
+export const rangeLiveCombos = async (
+  board: number[],
+  deadCards: number[]
+): Promise<number[]> => {
+  return await invoke("range_live_combos", { board, deadCards });
+};
+
+export type RangeOp =
+  | { op: "max" }
+  | { op: "min" }
+  | { op: "subtract" }
+  | { op: "multiply" }
+  | { op: "weighted_average"; weight: number };
+
+/** `rhs` is either the index of a stored range or a range string. */
+export const rangeCombine = async (
+  lhs: number,
+  rhs: number | string,
+  op: RangeOp,
+  target: number
+): Promise<string | null> => {
+  return await invokeError("range_combine", {
+    sessionId,
+    lhs,
+    rhs,
+    op,
+    target,
+  });
+};
+
+export const rangeScale = async (
+  player: number,
+  factor: number,
+  target: number
+) => {
+  await invoke("range_scale", { sessionId, player, factor, target });
+};
+
+export const rangeClamp = async (
+  player: number,
+  min: number,
+  max: number,
+  target: number
+): Promise<string | null> => {
+  return await invokeError("range_clamp", {
+    sessionId,
+    player,
+    min,
+    max,
+    target,
+  });
+};
+
+export type RangeFormat =
+  | "auto"
+  | "sanitized"
+  | "pio"
+  | "gtoplus"
+  | "combos"
+  | "csv";
+
+export type RangeImportResult = {
+  format: RangeFormat;
+  unparsed: string[];
+};
+
+export const rangeImport = async (
+  player: number,
+  text: string,
+  format: RangeFormat
+): Promise<RangeImportResult | string> => {
+  try {
+    return await invoke("range_import", { sessionId, player, text, format });
+  } catch (error) {
+    return errorToString(error);
+  }
+};
+
+export const rangeExport = async (
+  player: number,
+  format: RangeFormat
+): Promise<string> => {
+  return await invoke("range_export", { sessionId, player, format });
+};
+
+/** Each returns whether undo and redo are available. */
+export const rangeUndo = async (player: number): Promise<[boolean, boolean]> => {
+  return await invoke("range_undo", { sessionId, player });
+};
+
+export const rangeRedo = async (player: number): Promise<[boolean, boolean]> => {
+  return await invoke("range_redo", { sessionId, player });
+};
+
+export const rangeHistoryState = async (
+  player: number
+): Promise<[boolean, boolean]> => {
+  return await invoke("range_history_state", { sessionId, player });
+};
+
+export type TopPercentOptions = {
+  board: number[];
+  by: { by: "equity"; opponent: number } | { by: "strength" };
+  from: number;
+  to: number;
+};
+
+export const rangeTopPercent = async (
+  player: number,
+  options: TopPercentOptions,
+  target: number
+): Promise<string | null> => {
+  return await invokeError("range_top_percent", {
+    sessionId,
+    player,
+    options,
+    target,
+  });
+};
+
+export type RangeDiff = {
+  num_combos: [number, number];
+  class_delta: number[];
+  combo_difference: number;
+  overlap: number;
+  only_in: [string[], string[]];
+};
+
+export const rangeDiff = async (
+  lhs: number,
+  rhs: number
+): Promise<RangeDiff> => {
+  return await invoke("range_diff", { sessionId, lhs, rhs });
+};
+
+export type EquityResult = {
+  equity: [number, number];
+  combo_equity: [(number | null)[], (number | null)[]];
+  class_equity: [(number | null)[], (number | null)[]];
+};
+
+export const rangeEquity = async (
+  players: [number, number],
+  board: number[],
+  deadCards: number[]
+): Promise<EquityResult> => {
+  return await invoke("range_equity", {
+    sessionId,
+    players,
+    board,
+    deadCards,
+  });
 };
 
 /* Action Tree */
 
+export type TreeConfigSpec = {
+  starting_pot: number;
+  effective_stack: number;
+  rake_rate: number;
+  rake_cap: number;
+  donk_option: boolean;
+  oop_flop_bet: string;
+  oop_flop_raise: string;
+  oop_turn_bet: string;
+  oop_turn_raise: string;
+  oop_turn_donk: string;
+  oop_river_bet: string;
+  oop_river_raise: string;
+  oop_river_donk: string;
+  ip_flop_bet: string;
+  ip_flop_raise: string;
+  ip_turn_bet: string;
+  ip_turn_raise: string;
+  ip_river_bet: string;
+  ip_river_raise: string;
+  add_allin_threshold: number;
+  force_allin_threshold: number;
+  merging_threshold: number;
+  added_lines: string;
+  removed_lines: string;
+};
+
+export type GameSpec = TreeConfigSpec & {
+  board: number[];
+};
+
+export type StreetStats = {
+  decision_nodes: number;
+  chance_nodes: number;
+  terminal_nodes: number;
+  max_raises: number;
+  bet_sizes: number;
+};
+
+export type TreeStats = {
+  streets: StreetStats[];
+  max_raises: number;
+};
+
+/** `boardLen` may be less than 3 while the board is being entered. */
 export const treeNew = async (
   boardLen: number,
-  startingPot: number,
-  effectiveStack: number,
-  donkOption: boolean,
-  oopFlopBet: string,
-  oopFlopRaise: string,
-  oopTurnBet: string,
-  oopTurnRaise: string,
-  oopTurnDonk: string,
-  oopRiverBet: string,
-  oopRiverRaise: string,
-  oopRiverDonk: string,
-  ipFlopBet: string,
-  ipFlopRaise: string,
-  ipTurnBet: string,
-  ipTurnRaise: string,
-  ipRiverBet: string,
-  ipRiverRaise: string,
-  addAllinThreshold: number,
-  forceAllinThreshold: number,
-  mergingThreshold: number,
-  addedLines: string,
-  removedLines: string
-): Promise<boolean> => {
-  return await invoke("tree_new", {
-    boardLen,
-    startingPot,
-    effectiveStack,
-    donkOption,
-    oopFlopBet,
-    oopFlopRaise,
-    oopTurnBet,
-    oopTurnRaise,
-    oopTurnDonk,
-    oopRiverBet,
-    oopRiverRaise,
-    oopRiverDonk,
-    ipFlopBet,
-    ipFlopRaise,
-    ipTurnBet,
-    ipTurnRaise,
-    ipRiverBet,
-    ipRiverRaise,
-    addAllinThreshold,
-    forceAllinThreshold,
-    mergingThreshold,
-    addedLines,
-    removedLines,
+  spec: TreeConfigSpec
+): Promise<TreeStats | null> => {
+  try {
+    return await invoke("tree_new", { sessionId, boardLen, spec });
+  } catch {
+    return null;
+  }
+};
+
+export const treeValidateSpec = async (
+  spec: TreeConfigSpec
+): Promise<BackendError[]> => {
+  return await invoke("tree_validate_spec", { spec });
+};
+
+export const treeStats = async (): Promise<TreeStats> => {
+  return await invoke("tree_stats", { sessionId });
+};
+
+export const treeEstimateMemory = async (
+  board: number[]
+): Promise<number[]> => {
+  return await invoke("tree_estimate_memory", { sessionId, board });
+};
+
+export type BulkEditResult = {
+  affected: string[];
+  skipped: [string, string][];
+};
+
+/** Adds or removes `action` (e.g. "B33%") at every node matching `pattern`. */
+export const treeEditMatching = async (
+  pattern: string,
+  operation: "add" | "remove",
+  action: string
+): Promise<BulkEditResult> => {
+  return await invoke("tree_edit_matching", {
+    sessionId,
+    pattern,
+    operation,
+    action,
   });
 };
 
 export const treeAddedLines = async (): Promise<string> => {
-  return await invoke("tree_added_lines");
+  return await invoke("tree_added_lines", { sessionId });
 };
 
 export const treeRemovedLines = async (): Promise<string> => {
-  return await invoke("tree_removed_lines");
+  return await invoke("tree_removed_lines", { sessionId });
 };
 
 export const treeInvalidTerminals = async (): Promise<string> => {
-  return await invoke("tree_invalid_terminals");
+  return await invoke("tree_invalid_terminals", { sessionId });
 };
 
 export const treeActions = async (): Promise<string[]> => {
-  return await invoke("tree_actions");
+  return await invoke("tree_actions", { sessionId });
 };
 
 export const treeIsTerminalNode = async (): Promise<boolean> => {
-  return await invoke("tree_is_terminal_node");
+  return await invoke("tree_is_terminal_node", { sessionId });
 };
 
 export const treeIsChanceNode = async (): Promise<boolean> => {
-  return await invoke("tree_is_chance_node");
+  return await invoke("tree_is_chance_node", { sessionId });
 };
 
 export const treeBackToRoot = async () => {
-  await invoke("tree_back_to_root");
+  await invoke("tree_back_to_root", { sessionId });
 };
 
 export const treeApplyHistory = async (line: string[]) => {
-  await invoke("tree_apply_history", { line });
+  await invoke("tree_apply_history", { sessionId, line });
 };
 
 export const treePlay = async (action: string): Promise<number> => {
-  return await invoke("tree_play", { action });
+  return await invoke("tree_play", { sessionId, action });
 };
 
 export const treeTotalBetAmount = async (): Promise<number[]> => {
-  return await invoke("tree_total_bet_amount");
+  return await invoke("tree_total_bet_amount", { sessionId });
 };
 
 export const treeAddBetAction = async (amount: number, isRaise: boolean) => {
-  await invoke("tree_add_bet_action", { amount, isRaise });
+  await invoke("tree_add_bet_action", { sessionId, amount, isRaise });
 };
 
 export const treeRemoveCurrentNode = async () => {
-  await invoke("tree_remove_current_node");
+  await invoke("tree_remove_current_node", { sessionId });
 };
 
 export const treeDeleteAddedLine = async (line: string) => {
-  await invoke("tree_delete_added_line", { line });
+  await invoke("tree_delete_added_line", { sessionId, line });
 };
 
 export const treeDeleteRemovedLine = async (line: string) => {
-  await invoke("tree_delete_removed_line", { line });
+  await invoke("tree_delete_removed_line", { sessionId, line });
+};
+
+export type TreeExportFormat = "dot" | "json";
+
+export const treeExport = async (
+  format: TreeExportFormat
+): Promise<string> => {
+  return await invoke("tree_export", { sessionId, format });
 };
 
 /* Bunching effect */
 
 export const bunchingInit = async (board: number[]): Promise<string | null> => {
-  return await invoke("bunching_init", { board });
+  return await invokeError("bunching_init", { sessionId, board });
 };
 
 export const bunchingClear = async () => {
-  await invoke("bunching_clear");
+  await invoke("bunching_clear", { sessionId });
 };
 
 export const bunchingProgress = async (): Promise<number[]> => {
-  return await invoke("bunching_progress");
+  return await invoke("bunching_progress", { sessionId });
 };
 
 /* Game */
 
-export const gameInit = async (
-  board: number[],
-  startingPot: number,
-  effectiveStack: number,
-  rakeRate: number,
-  rakeCap: number,
-  donkOption: boolean,
-  oopFlopBet: string,
-  oopFlopRaise: string,
-  oopTurnBet: string,
-  oopTurnRaise: string,
-  oopTurnDonk: string,
-  oopRiverBet: string,
-  oopRiverRaise: string,
-  oopRiverDonk: string,
-  ipFlopBet: string,
-  ipFlopRaise: string,
-  ipTurnBet: string,
-  ipTurnRaise: string,
-  ipRiverBet: string,
-  ipRiverRaise: string,
-  addAllinThreshold: number,
-  forceAllinThreshold: number,
-  mergingThreshold: number,
-  addedLines: string,
-  removedLines: string
+export const gameInit = async (spec: GameSpec): Promise<string | null> => {
+  return await invokeError("game_init", { sessionId, spec });
+};
+
+/** Builds the game from the tree edited with the `tree*` commands as-is. */
+export const gameInitFromTree = async (
+  board: number[]
 ): Promise<string | null> => {
-  return await invoke("game_init", {
-    board,
-    startingPot,
-    effectiveStack,
-    rakeRate,
-    rakeCap,
-    donkOption,
-    oopFlopBet,
-    oopFlopRaise,
-    oopTurnBet,
-    oopTurnRaise,
-    oopTurnDonk,
-    oopRiverBet,
-    oopRiverRaise,
-    oopRiverDonk,
-    ipFlopBet,
-    ipFlopRaise,
-    ipTurnBet,
-    ipTurnRaise,
-    ipRiverBet,
-    ipRiverRaise,
-    addAllinThreshold,
-    forceAllinThreshold,
-    mergingThreshold,
-    addedLines,
-    removedLines,
+  return await invokeError("game_init_from_tree", { sessionId, board });
+};
+
+export const gameValidateSpec = async (
+  spec: GameSpec
+): Promise<BackendError[]> => {
+  return await invoke("game_validate_spec", { spec });
+};
+
+export type NodeRangeOptions = {
+  normalize: boolean;
+  min_weight: number;
+};
+
+/** Stores the range in the slot `target` if given, and returns it as a string. */
+export const gameNodeRange = async (
+  player: number,
+  options: NodeRangeOptions,
+  target: number | null
+): Promise<string> => {
+  return await invoke("game_node_range", {
+    sessionId,
+    player,
+    options,
+    target,
+  });
+};
+
+/**
+ * Re-solves the subgame from the current node, which must be the first decision
+ * node of a street. Returns the ID of the session holding the re-solved game.
+ */
+export const gameResolveSubgame = async (
+  params: Partial<TreeConfigSpec>,
+  newSession: boolean,
+  name: string | null
+): Promise<number> => {
+  return await invoke("game_resolve_subgame", {
+    sessionId,
+    params,
+    newSession,
+    name,
   });
 };
 
 export const gamePrivateCards = async (): Promise<number[][]> => {
-  return await invoke("game_private_cards");
+  return await invoke("game_private_cards", { sessionId });
 };
 
 export const gameMemoryUsage = async (): Promise<number[]> => {
-  return await invoke("game_memory_usage");
+  return await invoke("game_memory_usage", { sessionId });
 };
 
 export const gameMemoryUsageBunching = async (): Promise<number> => {
-  return await invoke("game_memory_usage_bunching");
+  return await invoke("game_memory_usage_bunching", { sessionId });
 };
 
 export const gameAllocateMemory = async (enableCompression: boolean) => {
-  await invoke("game_allocate_memory", { enableCompression });
+  await invoke("game_allocate_memory", { sessionId, enableCompression });
 };
 
 export const gameSetBunching = async (): Promise<string | null> => {
-  return await invoke("game_set_bunching");
+  return await invokeError("game_set_bunching", { sessionId });
 };
 
 export const gameSolveStep = async (currentIteration: number) => {
-  await invoke("game_solve_step", { currentIteration });
+  await invoke("game_solve_step", { sessionId, currentIteration });
 };
 
 export const gameExploitability = async (): Promise<number> => {
-  return await invoke("game_exploitability");
+  return await invoke("game_exploitability", { sessionId });
 };
 
 export const gameFinalize = async () => {
-  await invoke("game_finalize");
+  await invoke("game_finalize", { sessionId });
+};
+
+export type SolveOptions = {
+  targetExploitability: number;
+  maxIterations: number;
+  timeLimit: number | null;
+  exploitabilityInterval: number;
+};
+
+export type SolveProgress = {
+  session_id?: number;
+  status: "idle" | "running" | "paused" | "finished" | "cancelled";
+  iteration: number;
+  exploitability: number;
+  elapsed: number;
+};
+
+export const gameSolveStart = async (options: SolveOptions) => {
+  await invoke("game_solve_start", {
+    sessionId,
+    options: {
+      target_exploitability: options.targetExploitability,
+      max_iterations: options.maxIterations,
+      time_limit: options.timeLimit,
+      exploitability_interval: options.exploitabilityInterval,
+    },
+  });
+};
+
+export const gameSolvePause = async () => {
+  await invoke("game_solve_pause", { sessionId });
+};
+
+export const gameSolveResume = async () => {
+  await invoke("game_solve_resume", { sessionId });
+};
+
+export const gameSolveCancel = async () => {
+  await invoke("game_solve_cancel", { sessionId });
+};
+
+export const gameSolveProgress = async (): Promise<SolveProgress> => {
+  return await invoke("game_solve_progress", { sessionId });
+};
+
+export const onSolveProgress = async (
+  callback: (progress: SolveProgress) => void
+): Promise<UnlistenFn> => {
+  return await listen<SolveProgress>("solve-progress", (event) =>
+    callback(event.payload)
+  );
+};
+
+export const gameSave = async (
+  path: string,
+  memo: string,
+  compressionLevel: number | null,
+  targetStreet: number | null
+): Promise<string | null> => {
+  return await invokeError("game_save", {
+    sessionId,
+    path,
+    memo,
+    compressionLevel,
+    targetStreet,
+  });
+};
+
+export const gameLoad = async (
+  path: string,
+  maxMemoryUsage: number | null
+): Promise<string> => {
+  return await invoke("game_load", { sessionId, path, maxMemoryUsage });
+};
+
+export type NodeLock = {
+  history: number[];
+  player: number;
+  num_actions: number;
+  strategy: number[];
+};
+
+export const gameLockStrategy = async (
+  history: number[],
+  strategy: Float32Array | number[]
+): Promise<string | null> => {
+  return await invokeError("game_lock_strategy", {
+    sessionId,
+    history,
+    strategy: Array.from(strategy),
+  });
+};
+
+export const gameLockHands = async (
+  history: number[],
+  hands: number[],
+  frequencies: number[]
+): Promise<string | null> => {
+  return await invokeError("game_lock_hands", {
+    sessionId,
+    history,
+    hands,
+    frequencies,
+  });
+};
+
+export const gameUnlockStrategy = async (
+  history: number[]
+): Promise<string | null> => {
+  return await invokeError("game_unlock_strategy", { sessionId, history });
+};
+
+export const gameLockedNodes = async (): Promise<NodeLock[]> => {
+  return await invoke("game_locked_nodes", { sessionId });
 };
 
 export const gameApplyHistory = async (history: number[]) => {
-  await invoke("game_apply_history", { history });
+  await invoke("game_apply_history", { sessionId, history });
 };
 
 export const gameTotalBetAmount = async (
   append: number[]
 ): Promise<number[]> => {
-  return await invoke("game_total_bet_amount", { append });
+  return await invoke("game_total_bet_amount", { sessionId, append });
 };
 
 export const gameActionsAfter = async (append: number[]): Promise<string[]> => {
-  return await invoke("game_actions_after", { append });
+  return await invoke("game_actions_after", { sessionId, append });
 };
 
 export const gamePossibleCards = async (): Promise<bigint> => {
-  return BigInt(await invoke("game_possible_cards"));
+  return BigInt(await invoke("game_possible_cards", { sessionId }));
 };
 
 type ResultsResponse = {
@@ -302,7 +759,9 @@ type ResultsResponse = {
 };
 
 export const gameGetResults = async (): Promise<Results> => {
-  const results: ResultsResponse = await invoke("game_get_results");
+  const results: ResultsResponse = await invoke("game_get_results", {
+    sessionId,
+  });
   return {
     currentPlayer: results.current_player,
     numActions: results.num_actions,
@@ -328,6 +787,39 @@ type ChanceReportsResponse = {
   strategy: number[];
 };
 
+export type CategoryStats = {
+  category: string;
+  combos: number;
+  weight: number;
+  strategy: number[];
+  action_ev: number[];
+};
+
+export type HandCategoryReport = {
+  player: string;
+  actions: string[];
+  made: CategoryStats[];
+  draws: CategoryStats[];
+};
+
+export const gameHandCategories = async (): Promise<HandCategoryReport> => {
+  return await invoke("game_hand_categories", { sessionId });
+};
+
+export const gameExportNode = async (
+  path: string,
+  format: "csv" | "json"
+): Promise<string | null> => {
+  return await invokeError("game_export_node", { sessionId, path, format });
+};
+
+export const gameDumpTree = async (
+  path: string,
+  chanceCards: number[] | null
+): Promise<number> => {
+  return await invoke("game_dump_tree", { sessionId, path, chanceCards });
+};
+
 export const gameGetChanceReports = async (
   append: number[],
   currentPlayer: "oop" | "ip" | "terminal",
@@ -335,7 +827,7 @@ export const gameGetChanceReports = async (
 ): Promise<ChanceReports> => {
   const reports: ChanceReportsResponse = await invoke(
     "game_get_chance_reports",
-    { append, numActions }
+    { sessionId, append, numActions }
   );
   return {
     currentPlayer,